@@ -1,9 +1,54 @@
-use std::io::{self, Write};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
 
 use colored::*;
 use prettytable::{Cell, Row, Table};
 use serde::{Deserialize, Serialize};
 
+// ======
+// Errors
+// ======
+
+#[derive(Debug)]
+enum AppError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io(e) => write!(f, "I/O error: {e}"),
+            AppError::Json(e) => write!(f, "JSON error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Io(e) => Some(e),
+            AppError::Json(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Json(e)
+    }
+}
+
 // ======================
 // Domain types & helpers
 // ======================
@@ -14,137 +59,890 @@ struct Task {
     title: String,
     description: String,
     status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    // A person or event outside the list this task is stuck on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    waiting_on: Option<String>,
+    // Effort estimate in story points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    points: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    risk: Option<RiskLevel>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 enum TaskStatus {
     Todo,
     InProgress,
+    Blocked,
     Done,
 }
 
+// Declared lowest first, so `Ord` ranks High above Low
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
 impl Task {
     fn new(id: u32, title: String, description: String, status: TaskStatus) -> Task {
-        Task { id, title, description, status }
+        Task { id, title, description, status, url: None, pinned: false, waiting_on: None, points: None, risk: None }
+    }
+
+    // A new task carrying on this one's work (split off from it, or the next
+    // step after it). It keeps what describes the work as a whole: pin, risk
+    // and link. Points and waiting_on are about this task's own share, so
+    // the new one starts without them.
+    fn spin_off(&self, id: u32, title: String, description: String, status: TaskStatus) -> Task {
+        let mut task = Task::new(id, title, description, status);
+        task.pinned = self.pinned;
+        task.risk = self.risk;
+        task.url = self.url.clone();
+        task
     }
 }
 
 use dialoguer::{theme::ColorfulTheme, Input, Select, Confirm};
 
-fn prompt_status(theme: &ColorfulTheme, prompt: &str) -> Option<TaskStatus> {
-    let statuses = ["Todo", "InProgress", "Done"];
-    let idx = Select::with_theme(theme)
-        .with_prompt(prompt)
-        .items(&statuses)
-        .default(0)
-        .interact()
-        .ok()?;
+// println!/eprintln! for whatever an action reports. While the full-screen
+// menu is up the text goes into the dialog box drawn over it (`Overlay`)
+// rather than onto the alternate screen, where it would scribble over the
+// menu.
+macro_rules! say {
+    () => { say(String::new(), false) };
+    ($($arg:tt)*) => { say(format!($($arg)*), false) };
+}
+
+macro_rules! say_err {
+    ($($arg:tt)*) => { say(format!($($arg)*), true) };
+}
+
+fn say(text: String, to_stderr: bool) {
+    let shown = with_overlay(|o| o.lines.extend(text.split('\n').map(String::from)));
+    if shown.is_none() {
+        if to_stderr { eprintln!("{text}") } else { println!("{text}") }
+    }
+}
+
+// dialoguer needs a terminal to draw on. Set once in main when there isn't
+// one (or the plain menu is in use), so prompts read plain lines from stdin
+// instead and pipes/CI see the same questions as numbered text.
+static PLAIN_PROMPTS: AtomicBool = AtomicBool::new(false);
+
+fn plain_prompts() -> bool {
+    PLAIN_PROMPTS.load(Ordering::Relaxed)
+}
+
+// Prints `prompt` and reads one line without its line ending; None at EOF
+fn read_plain_line(prompt: &str) -> Option<String> {
+    print!("{prompt}");
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(['\r', '\n']).to_string()),
+    }
+}
+
+// Free text, starting out as `initial` (a plain prompt keeps it on an empty
+// line). Input that `check` rejects is asked for again. None if cancelled.
+fn prompt_text(theme: &ColorfulTheme, prompt: &str, initial: &str, check: impl Fn(&str) -> Result<(), &'static str>) -> Option<String> {
+    if let Some(answer) = with_overlay(|o| o.text(prompt, initial, &check)) {
+        return answer;
+    }
+    if !plain_prompts() {
+        return Input::with_theme(theme)
+            .with_prompt(prompt)
+            .with_initial_text(initial)
+            .allow_empty(true)
+            .validate_with(|s: &String| check(s))
+            .interact_text()
+            .ok();
+    }
+    let shown = if initial.is_empty() { format!("{prompt}: ") } else { format!("{prompt} [{initial}]: ") };
+    loop {
+        let line = read_plain_line(&shown)?;
+        let value = if line.is_empty() { initial.to_string() } else { line };
+        match check(&value) {
+            Ok(()) => return Some(value),
+            Err(msg) => println!("{msg}"),
+        }
+    }
+}
+
+// Index of the chosen item, or None if cancelled. A plain prompt numbers
+// the items from 1 and takes `default` on an empty line.
+fn prompt_select<T: fmt::Display>(theme: &ColorfulTheme, prompt: &str, items: &[T], default: usize) -> Option<usize> {
+    if let Some(answer) = with_overlay(|o| o.select(prompt, &items.iter().map(T::to_string).collect::<Vec<_>>(), default)) {
+        return answer;
+    }
+    if !plain_prompts() {
+        return Select::with_theme(theme)
+            .with_prompt(prompt)
+            .items(items)
+            .default(default)
+            .interact()
+            .ok();
+    }
+    println!("{prompt}");
+    for (i, item) in items.iter().enumerate() {
+        println!("  {}) {item}", i + 1);
+    }
+    loop {
+        let line = read_plain_line(&format!("Choose 1-{} [{}]: ", items.len(), default + 1))?;
+        match line.trim() {
+            "" => return Some(default),
+            n => match n.parse::<usize>() {
+                Ok(n) if (1..=items.len()).contains(&n) => return Some(n - 1),
+                _ => println!("Enter a number from 1 to {}.", items.len()),
+            },
+        }
+    }
+}
+
+fn prompt_status(theme: &ColorfulTheme, prompt: &str, default: &TaskStatus) -> Option<TaskStatus> {
+    let statuses = ["Todo", "InProgress", "Blocked", "Done"];
+    let default_idx = statuses.iter().position(|s| *s == format!("{default:?}")).unwrap_or(0);
+    let idx = prompt_select(theme, prompt, &statuses, default_idx)?;
     Some(match statuses[idx] {
         "Todo" => TaskStatus::Todo,
         "InProgress" => TaskStatus::InProgress,
+        "Blocked" => TaskStatus::Blocked,
         _ => TaskStatus::Done,
     })
 }
 
-fn prompt_add_task(next_id: u32) -> Option<Task> {
+fn required_title(s: &str) -> Result<(), &'static str> {
+    if s.trim().is_empty() { Err("Title cannot be empty") } else { Ok(()) }
+}
+
+fn prompt_add_task(next_id: u32, default_status: &TaskStatus) -> Option<Task> {
     let theme = ColorfulTheme::default();
 
-    let title: String = Input::with_theme(&theme)
-        .with_prompt("Title")
-        .validate_with(|s: &String| {
-            if s.trim().is_empty() { Err("Title cannot be empty") } else { Ok(()) }
-        })
-        .interact_text()
-        .ok()?;
+    let title = prompt_text(&theme, "Title", "", required_title)?;
+
+    let description = prompt_text(&theme, "Description", "", |_| Ok(()))?;
+
+    let status = prompt_status(&theme, "Status", default_status)?;
+
+    let url = prompt_text(&theme, "URL (optional)", "", |s| {
+        let s = s.trim();
+        if s.is_empty() || s.starts_with("http://") || s.starts_with("https://") {
+            Ok(())
+        } else {
+            Err("URL must start with http:// or https://")
+        }
+    })?;
+
+    let points = prompt_text(&theme, "Points (optional)", "", |s| {
+        let s = s.trim();
+        if s.is_empty() || s.parse::<u8>().is_ok() { Ok(()) } else { Err("Points must be a whole number from 0 to 255") }
+    })?;
+
+    let mut task = Task::new(next_id, title.trim().into(), description.trim().into(), status);
+    let url = url.trim();
+    if !url.is_empty() {
+        task.url = Some(url.into());
+    }
+    task.points = points.trim().parse().ok();
+
+    let risks = ["None", "Low", "Medium", "High"];
+    let risk = prompt_select(&theme, "Risk (optional)", &risks, 0)?;
+    task.risk = match risk {
+        1 => Some(RiskLevel::Low),
+        2 => Some(RiskLevel::Medium),
+        3 => Some(RiskLevel::High),
+        _ => None,
+    };
+    Some(task)
+}
+
+// Shortens `s` to at most `max` characters, marking the cut with "…"
+fn truncate_with_ellipsis(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut out: String = s.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
+
+// Asks for the second half of a split: a title for the new task and, by
+// editing the original's description in place, what the original keeps.
+// Returns (new title, original's new description).
+fn prompt_split_task(original: &Task) -> Option<(String, String)> {
+    let theme = ColorfulTheme::default();
+
+    let title = prompt_text(&theme, "Title for the new task", "", required_title)?;
+
+    let description = prompt_text(&theme, &format!("Description kept on #{}", original.id), &original.description, |_| Ok(()))?;
+
+    Some((title.trim().into(), description.trim().into()))
+}
 
-    let description: String = Input::with_theme(&theme)
-        .with_prompt("Description")
-        .allow_empty(true)
-        .interact_text()
-        .ok()?;
+// Title and description for the next step after `done`. The description
+// starts out pointing back at the finished task.
+fn prompt_follow_up(done: &Task) -> Option<(String, String)> {
+    let theme = ColorfulTheme::default();
+
+    let title = prompt_text(&theme, &format!("Follow-up to #{}", done.id), "", required_title)?;
 
-    let status = prompt_status(&theme, "Status")?;
+    let description = prompt_text(&theme, "Description", &format!("Follow-up to #{} ({})", done.id, done.title), |_| Ok(()))?;
+
+    Some((title.trim().into(), description.trim().into()))
+}
 
-    Some(Task::new(next_id, title.trim().into(), description.trim().into(), status))
+// Outer `None` means cancelled; `Some(None)` clears the note
+fn prompt_waiting_on(task: &Task) -> Option<Option<String>> {
+    let theme = ColorfulTheme::default();
+    let current = task.waiting_on.as_deref().unwrap_or_default();
+    // A plain prompt keeps pre-filled text on an empty line, and empty has to
+    // mean "clear" here, so it only gets the current value in the question
+    let who = if plain_prompts() {
+        let prompt = match current {
+            "" => format!("#{} is waiting on (empty to clear)", task.id),
+            who => format!("#{} is waiting on {who}; now (empty to clear)", task.id),
+        };
+        prompt_text(&theme, &prompt, "", |_| Ok(()))?
+    } else {
+        prompt_text(&theme, &format!("#{} is waiting on (empty to clear)", task.id), current, |_| Ok(()))?
+    };
+    let who = who.trim();
+    Some(if who.is_empty() { None } else { Some(who.to_string()) })
 }
 
-fn prompt_select_task_id(tasks: &[Task], prompt: &str) -> Option<u32> {
+fn prompt_select_task_id(tasks: &[Task], prompt: &str, config: &Config) -> Option<u32> {
     if tasks.is_empty() {
-        println!("No tasks available.");
+        say!("No tasks available.");
         return None;
     }
     let theme = ColorfulTheme::default();
+    // Long titles would wrap inside the selector; keep ID and status intact
+    // and cut the title to whatever width is left (minus the theme's cursor,
+    // or the dialog box's margin, border and cursor over the TUI)
+    let cols = crossterm::terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+    let cols = if with_overlay(|_| ()).is_some() { cols.saturating_sub(6) } else { cols };
     let items: Vec<String> = tasks.iter()
-        .map(|t| format!("#{:<3} {:<12} {}", t.id, format!("{:?}", t.status), t.title))
+        .map(|t| {
+            let symbol = if config.ascii_symbols { status_symbol_ascii(&t.status) } else { status_symbol(&t.status) };
+            let status = format!("{symbol} {:<12}", format!("{:?}", t.status));
+            let id = format_id(t.id, config.id_display_width);
+            let prefix = format!("#{id:<3} {status} ");
+            let room = cols.saturating_sub(prefix.chars().count() + 4);
+            format!("#{id:<3} {} {}", paint_status(&t.status, &status), truncate_with_ellipsis(&t.title, room))
+        })
         .collect();
 
-    let idx = Select::with_theme(&theme)
-        .with_prompt(prompt)
-        .items(&items)
-        .default(0)
-        .interact()
-        .ok()?;
+    let idx = prompt_select(&theme, prompt, &items, 0)?;
     Some(tasks[idx].id)
 }
 
-fn prompt_confirm(theme: &ColorfulTheme, msg: &str) -> bool {
-    Confirm::with_theme(theme)
-        .with_prompt(msg)
-        .default(true)
-        .interact()
-        .unwrap_or(false)
+// `default` is what a bare Enter answers; keep it `false` for anything
+// destructive
+fn prompt_confirm(theme: &ColorfulTheme, msg: &str, default: bool) -> bool {
+    if let Some(answer) = with_overlay(|o| o.confirm(msg, default)) {
+        return answer;
+    }
+    if !plain_prompts() {
+        return Confirm::with_theme(theme)
+            .with_prompt(msg)
+            .default(default)
+            .interact()
+            .unwrap_or(false);
+    }
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    loop {
+        let Some(line) = read_plain_line(&format!("{msg} {hint} ")) else { return false };
+        match line.trim().to_ascii_lowercase().as_str() {
+            "" => return default,
+            "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => println!("Please answer y or n."),
+        }
+    }
 }
 
-// fn parse_status(s: &str) -> Option<TaskStatus> {
-//     match s.trim().to_ascii_lowercase().as_str() {
-//         "todo" => Some(TaskStatus::Todo),
-//         "inprogress" | "in_progress" | "in progress" => Some(TaskStatus::InProgress),
-//         "done" => Some(TaskStatus::Done),
-//         _ => None,
-//     }
-// }
+fn parse_status(s: &str) -> Option<TaskStatus> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "todo" => Some(TaskStatus::Todo),
+        "inprogress" | "in_progress" | "in progress" => Some(TaskStatus::InProgress),
+        "blocked" => Some(TaskStatus::Blocked),
+        "done" => Some(TaskStatus::Done),
+        _ => None,
+    }
+}
 
 fn add_task(tasks: &mut Vec<Task>, task: Task) {
     tasks.push(task);
-    println!("Task added successfully.");
+    say!("Task added successfully.");
 }
 
 fn remove_task(tasks: &mut Vec<Task>, id: u32) {
     let before = tasks.len();
     tasks.retain(|t| t.id != id);
     if tasks.len() < before {
-        println!("Task with ID {} removed successfully.", id);
+        say!("Task with ID {} removed successfully.", id);
     } else {
-        println!("Task with ID {} not found.", id);
+        say!("Task with ID {} not found.", id);
+    }
+}
+
+// Reads every `*.json` task file in `dir`, numbering the tasks from
+// `next_id`. Files that can't be read or parsed are skipped and listed, and
+// `own_files` (the app's data, config and state files) are never read.
+// Returns the tasks to add.
+fn import_dir(next_id: u32, dir: &str, own_files: &[std::path::PathBuf]) -> io::Result<Vec<Task>> {
+    // Never re-import our own files when pointed at their folder
+    let own_files: Vec<std::path::PathBuf> = own_files.iter().filter_map(|p| std::fs::canonicalize(p).ok()).collect();
+
+    let mut paths: Vec<std::path::PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+        .filter(|p| std::fs::canonicalize(p).is_ok_and(|p| !own_files.contains(&p)))
+        .collect();
+    paths.sort();
+
+    let mut next_id = next_id;
+    let mut imported = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+
+    for path in paths {
+        let batch = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| parse_tasks(&s).ok());
+        match batch {
+            Some(batch) => {
+                for mut t in batch {
+                    t.id = next_id;
+                    next_id += 1;
+                    imported.push(t);
+                }
+            }
+            None => skipped.push(path.display().to_string()),
+        }
+    }
+
+    if !skipped.is_empty() {
+        say_err!("{} {}", "Skipped files that could not be parsed:".yellow(), skipped.join(", "));
+    }
+    Ok(imported)
+}
+
+// One task per non-empty line of a text file, titled with the line. A
+// leading "x " (todo.txt style) marks the task Done. IDs start at
+// `next_id`. Returns the tasks to add.
+fn import_text(next_id: u32, path: &str) -> io::Result<Vec<Task>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut next_id = next_id;
+    let mut imported = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let (title, status) = match line.strip_prefix("x ") {
+            Some(rest) => (rest.trim(), TaskStatus::Done),
+            None => (line, TaskStatus::Todo),
+        };
+        if title.is_empty() {
+            continue;
+        }
+        imported.push(Task::new(next_id, title.to_string(), String::new(), status));
+        next_id += 1;
+    }
+    Ok(imported)
+}
+
+// Trims and collapses runs of whitespace: "  Buy   milk " -> "Buy milk"
+fn normalize_title(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Uppercases the first letter of each word and leaves the rest alone, so
+// acronyms like "API" survive
+fn title_case(s: &str) -> String {
+    s.split(' ')
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Applies the configured title clean-up to a freshly entered title
+fn tidy_title(title: String, config: &Config) -> String {
+    if !config.normalize_titles {
+        return title;
+    }
+    let title = normalize_title(&title);
+    if config.title_case { title_case(&title) } else { title }
+}
+
+// Swaps an empty description for `empty_description_placeholder`, if set
+fn tidy_description(description: String, config: &Config) -> String {
+    match &config.empty_description_placeholder {
+        Some(placeholder) if description.trim().is_empty() => placeholder.clone(),
+        _ => description,
+    }
+}
+
+// The description as entered, trimmed; the configured placeholder counts as
+// no description at all
+fn description_text<'a>(description: &'a str, config: &Config) -> &'a str {
+    let text = description.trim();
+    match &config.empty_description_placeholder {
+        Some(placeholder) if text == placeholder.trim() => "",
+        _ => text,
+    }
+}
+
+fn status_label(s: &TaskStatus) -> &'static str {
+    match s {
+        TaskStatus::Todo => "Todo",
+        TaskStatus::InProgress => "In Progress",
+        TaskStatus::Blocked => "Blocked",
+        TaskStatus::Done => "Done",
+    }
+}
+
+// "7", or "007" with a width of 3; 0 leaves the number unpadded
+fn format_id(id: u32, width: usize) -> String {
+    format!("{id:0width$}")
+}
+
+fn status_symbol(s: &TaskStatus) -> &'static str {
+    match s {
+        TaskStatus::Todo => "☐",
+        TaskStatus::InProgress => "◐",
+        TaskStatus::Blocked => "⊘",
+        TaskStatus::Done => "☑",
+    }
+}
+
+// For terminals whose fonts lack the symbols above (`ascii_symbols` in config)
+fn status_symbol_ascii(s: &TaskStatus) -> &'static str {
+    match s {
+        TaskStatus::Todo => "[ ]",
+        TaskStatus::InProgress => "[~]",
+        TaskStatus::Blocked => "[!]",
+        TaskStatus::Done => "[x]",
+    }
+}
+
+// Paints `text` in the status color used by the task table. Pad `text`
+// before calling this; escape codes would throw off `{:<N}` widths.
+fn paint_status(s: &TaskStatus, text: &str) -> String {
+    match s {
+        TaskStatus::Todo => text.yellow().to_string(),
+        TaskStatus::InProgress => text.blue().to_string(),
+        TaskStatus::Blocked => text.magenta().to_string(),
+        TaskStatus::Done => text.green().to_string(),
+    }
+}
+
+// Plain-text report for printing or mailing: tasks grouped by status with
+// aligned columns. Built without `colored` so no escape codes end up in it.
+fn export_report(tasks: &[Task]) -> String {
+    let id_w = tasks.iter().map(|t| t.id.to_string().len() + 1).max().unwrap_or(2);
+    let title_w = tasks.iter().map(|t| t.title.chars().count()).max().unwrap_or(5).max(5);
+
+    let mut out = String::new();
+    out.push_str("To-Do Report\n");
+    out.push_str("============\n");
+    // Tasks without an estimate don't count towards either total
+    let total: u32 = tasks.iter().filter_map(|t| t.points).map(u32::from).sum();
+    if tasks.iter().any(|t| t.points.is_some()) {
+        let done: u32 = tasks.iter()
+            .filter(|t| t.status == TaskStatus::Done)
+            .filter_map(|t| t.points)
+            .map(u32::from)
+            .sum();
+        let pct = (done * 100).checked_div(total).unwrap_or(0);
+        out.push_str(&format!("\nPoints: {done} of {total} done ({pct}%)\n"));
+    }
+    for status in [TaskStatus::Todo, TaskStatus::InProgress, TaskStatus::Blocked, TaskStatus::Done] {
+        let group: Vec<&Task> = tasks.iter().filter(|t| t.status == status).collect();
+        let heading = format!("{} ({})", status_label(&status), group.len());
+        out.push_str(&format!("\n{heading}\n{}\n", "-".repeat(heading.chars().count())));
+        if group.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for t in group {
+            let mut line = format!("  {:<id_w$}  {:<title_w$}  {}", format!("#{}", t.id), t.title, t.description);
+            if let Some(who) = &t.waiting_on {
+                line.push_str(&format!("  [waiting on {who}]"));
+            }
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// Task list in the shape Todoist's (and most importers') JSON expects:
+//
+//   title            -> content
+//   description      -> description, followed by any of the extras below
+//   status Done      -> checked: true (everything else is unchecked)
+//   status InProgress/Blocked -> label "in-progress" / "blocked"
+//   pinned           -> priority 4 (Todoist's p1); otherwise 1 (p4)
+//   waiting_on       -> label "waiting", plus a "Waiting on: …" line
+//   url, points      -> "Link: …" / "Points: …" lines in the description
+//
+// There's no due date or tag field to map. Nothing is dropped: fields the
+// importer has no slot for end up as text in the description.
+fn export_todoist_json(tasks: &[Task]) -> String {
+    let items: Vec<serde_json::Value> = tasks.iter()
+        .map(|t| {
+            let mut labels = Vec::new();
+            match t.status {
+                TaskStatus::InProgress => labels.push("in-progress"),
+                TaskStatus::Blocked => labels.push("blocked"),
+                TaskStatus::Todo | TaskStatus::Done => {}
+            }
+            let mut extra = Vec::new();
+            if let Some(who) = &t.waiting_on {
+                labels.push("waiting");
+                extra.push(format!("Waiting on: {who}"));
+            }
+            if let Some(url) = &t.url {
+                extra.push(format!("Link: {url}"));
+            }
+            if let Some(points) = t.points {
+                extra.push(format!("Points: {points}"));
+            }
+            let description = std::iter::once(t.description.clone())
+                .chain(extra)
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            serde_json::json!({
+                "content": t.title,
+                "description": description,
+                "checked": t.status == TaskStatus::Done,
+                "priority": if t.pinned { 4 } else { 1 },
+                "labels": labels,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&items).expect("JSON values always serialize")
+}
+
+// IDs that appear on more than one task, in first-seen order
+fn duplicate_ids(tasks: &[Task]) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    let mut dups = Vec::new();
+    for t in tasks {
+        if !seen.insert(t.id) && !dups.contains(&t.id) {
+            dups.push(t.id);
+        }
+    }
+    dups
+}
+
+// Groups of tasks whose (non-empty, trimmed) descriptions are identical, in
+// first-seen order. Placeholder descriptions count as empty.
+fn duplicate_descriptions<'a>(tasks: &'a [Task], config: &Config) -> Vec<Vec<&'a Task>> {
+    let mut groups: Vec<Vec<&Task>> = Vec::new();
+    for t in tasks.iter().filter(|t| !description_text(&t.description, config).is_empty()) {
+        match groups.iter_mut().find(|g| g[0].description.trim() == t.description.trim()) {
+            Some(group) => group.push(t),
+            None => groups.push(vec![t]),
+        }
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+// Lowercase, punctuation dropped, whitespace collapsed: "Buy  milk!" -> "buy milk"
+fn comparable_title(s: &str) -> String {
+    let kept: String = s.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    normalize_title(&kept)
+}
+
+// Pairs of tasks whose titles are at least `threshold` similar (0.0-1.0,
+// normalized Levenshtein), most similar first
+fn similar_titles(tasks: &[Task], threshold: f64) -> Vec<(&Task, &Task, f64)> {
+    let titles: Vec<String> = tasks.iter().map(|t| comparable_title(&t.title)).collect();
+    let mut pairs = Vec::new();
+    for i in 0..tasks.len() {
+        for j in i + 1..tasks.len() {
+            // Nothing left to compare ("!!!", emoji-only): two empties would score 1.0
+            if titles[i].is_empty() || titles[j].is_empty() {
+                continue;
+            }
+            let score = strsim::normalized_levenshtein(&titles[i], &titles[j]);
+            if score >= threshold {
+                pairs.push((&tasks[i], &tasks[j], score));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+    pairs
+}
+
+// Folds task `from` into `into`: its description is appended (unless it's
+// empty, the placeholder or the same), its pin, URL, points and waiting-on
+// note fill in whatever `into` lacks, the higher risk wins, and `from` is
+// removed
+fn merge_tasks(tasks: &mut Vec<Task>, into: u32, from: u32, config: &Config) {
+    let Some(source) = tasks.iter().position(|t| t.id == from) else { return };
+    let source = tasks.remove(source);
+    if let Some(target) = tasks.iter_mut().find(|t| t.id == into) {
+        let extra = description_text(&source.description, config);
+        if !extra.is_empty() && extra != target.description.trim() {
+            if description_text(&target.description, config).is_empty() {
+                target.description = extra.to_string();
+            } else {
+                target.description = format!("{}\n{extra}", target.description.trim_end());
+            }
+        }
+        target.pinned |= source.pinned;
+        target.risk = target.risk.max(source.risk);
+        if target.url.as_deref().is_none_or(|u| u.trim().is_empty()) {
+            target.url = source.url;
+        }
+        target.points = target.points.or(source.points);
+        target.waiting_on = target.waiting_on.take().or(source.waiting_on);
+    }
+}
+
+// Gives every repeated ID (after its first occurrence) a fresh, unused ID.
+// Returns the old -> new mappings that were applied.
+fn dedupe_ids(tasks: &mut [Task]) -> Vec<(u32, u32)> {
+    let mut seen = HashSet::new();
+    let mut next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+    let mut changes = Vec::new();
+    for t in tasks.iter_mut() {
+        if !seen.insert(t.id) {
+            changes.push((t.id, next_id));
+            t.id = next_id;
+            next_id += 1;
+        }
+    }
+    changes
+}
+
+// Optional table columns, named in config.json's `columns`. ID is always
+// shown and isn't listed here.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Column {
+    Title,
+    Description,
+    Status,
+    Points,
+    Url,
+}
+
+impl Column {
+    fn from_name(name: &str) -> Option<Column> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "title" => Some(Column::Title),
+            "description" => Some(Column::Description),
+            "status" => Some(Column::Status),
+            "points" => Some(Column::Points),
+            "url" => Some(Column::Url),
+            _ => None,
+        }
     }
 }
 
-fn list_tasks(tasks: &[Task]) {
+fn list_tasks<'a>(tasks: impl IntoIterator<Item = &'a Task>, config: &Config) {
+    let mut tasks: Vec<&Task> = tasks.into_iter().collect();
+    // Pinned tasks float to the top; the sort is stable so the rest keep their order
+    tasks.sort_by_key(|t| !t.pinned);
+    // Only spend a column on pins/points/URLs when at least one task uses it
+    let show_pin = tasks.iter().any(|t| t.pinned);
+    let show_points = tasks.iter().any(|t| t.points.is_some());
+    let show_url = tasks.iter().any(|t| t.url.is_some());
+    let columns: Vec<Column> = config.columns.iter()
+        .filter_map(|name| Column::from_name(name))
+        .filter(|c| match c {
+            Column::Points => show_points,
+            Column::Url => show_url,
+            _ => true,
+        })
+        .collect();
+
     let mut table = Table::new();
-    table.add_row(Row::new(vec![
-        Cell::new("ID").style_spec("bFg"),
-        Cell::new("Title").style_spec("bFc"),
-        Cell::new("Description").style_spec("bFy"),
-        Cell::new("Status").style_spec("bFr"),
-    ]));
+    let mut header = Vec::new();
+    if show_pin {
+        header.push(Cell::new("★").style_spec("bFy"));
+    }
+    header.push(Cell::new("ID").style_spec("bFg"));
+    for c in &columns {
+        header.push(match c {
+            Column::Title => Cell::new("Title").style_spec("bFc"),
+            Column::Description => Cell::new("Description").style_spec("bFy"),
+            Column::Status => Cell::new("Status").style_spec("bFr"),
+            Column::Points => Cell::new("Pts").style_spec("bFm"),
+            Column::Url => Cell::new("URL").style_spec("bFb"),
+        });
+    }
+    table.add_row(Row::new(header));
+
+    for t in tasks {
+        let mut row = Vec::new();
+        if show_pin {
+            row.push(Cell::new(if t.pinned { "★" } else { "" }).style_spec("Fy"));
+        }
+        row.push(Cell::new(&format_id(t.id, config.id_display_width)));
+        for c in &columns {
+            row.push(match c {
+                Column::Title if t.risk == Some(RiskLevel::High) => {
+                    Cell::new(&format!("{} {}", "⚠".red().bold(), t.title))
+                }
+                Column::Title => Cell::new(&t.title),
+                Column::Description => Cell::new(&t.description),
+                Column::Status => {
+                    let mut status = paint_status(&t.status, status_label(&t.status));
+                    if let Some(who) = &t.waiting_on {
+                        status.push_str(&format!("\n{}", format!("⏳ {who}").cyan().italic()));
+                    }
+                    Cell::new(&status)
+                }
+                Column::Points => Cell::new(&t.points.map(|p| p.to_string()).unwrap_or_default()).style_spec("r"),
+                Column::Url => Cell::new(t.url.as_deref().unwrap_or("")),
+            });
+        }
+        table.add_row(Row::new(row));
+    }
+    // printstd colors the header, but the dialog box only takes text
+    if with_overlay(|_| ()).is_some() {
+        say!("{table}");
+    } else {
+        table.printstd();
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum GroupKey {
+    Status,
+}
+
+// `None` means a flat list, which is also what we fall back to when the
+// selector can't run (e.g. stdin isn't a terminal)
+fn prompt_group_key(theme: &ColorfulTheme) -> Option<GroupKey> {
+    let options = ["None", "Status"];
+    let idx = prompt_select(theme, "Group by", &options, 0).unwrap_or(0);
+    match idx {
+        1 => Some(GroupKey::Status),
+        _ => None,
+    }
+}
+
+fn list_tasks_grouped<'a>(tasks: impl IntoIterator<Item = &'a Task>, by: GroupKey, config: &Config) {
+    // (label, tasks) in display order
+    let mut groups: Vec<(String, Vec<&Task>)> = Vec::new();
 
     for t in tasks {
-        let status = match t.status {
-            TaskStatus::Todo => "Todo".yellow().to_string(),
-            TaskStatus::InProgress => "In Progress".blue().to_string(),
-            TaskStatus::Done => "Done".green().to_string(),
+        let key = match by {
+            GroupKey::Status => format!("{:?}", t.status),
         };
-        table.add_row(Row::new(vec![
-            Cell::new(&t.id.to_string()),
-            Cell::new(&t.title),
-            Cell::new(&t.description),
-            Cell::new(&status),
-        ]));
+        match groups.iter_mut().find(|(label, _)| *label == key) {
+            Some((_, group)) => group.push(t),
+            None => groups.push((key, vec![t])),
+        }
+    }
+    match by {
+        // Keep the workflow order rather than first-seen order
+        GroupKey::Status => {
+            let rank = |label: &str| ["Todo", "InProgress", "Blocked", "Done"].iter().position(|s| *s == label);
+            groups.sort_by_key(|(label, _)| rank(label));
+        }
+    }
+
+    for (label, group) in groups {
+        say!("\n{} ({})", label.bold(), group.len());
+        list_tasks(group, config);
+    }
+}
+
+const EMPTY_LIST_ART: &str = "\
++---------------------------------------+
+|  \\o/   All clear!                     |
+|   |    Add your first task with '1'.  |
+|  / \\                                  |
++---------------------------------------+";
+
+// `empty_message` from config if set, else the art panel. Plain text when
+// output isn't a terminal.
+fn empty_list_message(config: &Config) -> String {
+    match &config.empty_message {
+        Some(msg) => msg.clone(),
+        None if io::stdout().is_terminal() => EMPTY_LIST_ART.cyan().to_string(),
+        None => "No tasks yet.".into(),
+    }
+}
+
+// Start-up banner naming the open High-risk tasks, cut to one line.
+// None when there aren't any.
+fn risk_banner(tasks: &[Task]) -> Option<String> {
+    let risky: Vec<String> = tasks.iter()
+        .filter(|t| t.status != TaskStatus::Done && t.risk == Some(RiskLevel::High))
+        .map(|t| format!("#{} {}", t.id, t.title))
+        .collect();
+    if risky.is_empty() {
+        return None;
+    }
+    Some(truncate_with_ellipsis(&format!("⚠ {} open High-risk task(s): {}", risky.len(), risky.join(", ")), 80))
+}
+
+// Checks `max_tasks` before `incoming` tasks are created and returns how
+// many may be. Over the limit this warns and either allows only what still
+// fits or asks, depending on `block_at_max_tasks`.
+fn allow_new_tasks(count: usize, incoming: usize, config: &Config) -> usize {
+    let Some(max) = config.max_tasks else { return incoming };
+    if count + incoming <= max {
+        return incoming;
+    }
+    let room = max.saturating_sub(count);
+    say!("{}", format!("You have {count} tasks (limit {max}).").yellow());
+    if config.block_at_max_tasks {
+        match room {
+            0 => say!("Finish or remove some before adding more."),
+            n => say!("Only {n} more fit; the rest are skipped."),
+        }
+        return room;
+    }
+    let theme = ColorfulTheme::default();
+    let question = match incoming {
+        1 => "Add one anyway?".to_string(),
+        n => format!("Add all {n} anyway? (No adds the {room} that fit)"),
+    };
+    if prompt_confirm(&theme, &question, false) { incoming } else { room }
+}
+
+fn allow_new_task(count: usize, config: &Config) -> bool {
+    allow_new_tasks(count, 1, config) == 1
+}
+
+// Marking one task Done is instant unless `confirm_complete` asks for a check.
+fn confirm_complete(id: u32, config: &Config) -> bool {
+    if !config.confirm_complete {
+        return true;
     }
-    table.printstd();
+    let theme = ColorfulTheme::default();
+    prompt_confirm(&theme, &format!("Mark task #{id} done?"), true)
 }
 
 fn wait_enter() {
+    if with_overlay(Overlay::pause).is_some() {
+        return;
+    }
     print!("\nPress Enter to continue...");
     let _ = io::stdout().flush();
     let mut s = String::new();
@@ -156,23 +954,24 @@ fn wait_enter() {
 // ==============
 
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
 
 use ratatui::{
     backend::CrosstermBackend,
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     symbols,
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Paragraph},
+    widgets::{Block, BorderType, Borders, Clear, List, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum MenuChoice {
     Add = 1,
     List = 2,
@@ -180,12 +979,70 @@ enum MenuChoice {
     Save = 4,
     Update = 5,
     Exit = 6,
+    Pin = 7,
+    Import = 8,
+    Report = 9,
+    OpenDir = 10,
+    Split = 11,
+    Waiting = 12,
+    Duplicates = 13,
+    Todoist = 14,
+    Risky = 15,
+    FollowUp = 16,
+    Reload = 17,
+    Copy = 18,
 }
 
+impl MenuChoice {
+    // Names used by the `menu` list in config.json
+    fn from_name(name: &str) -> Option<MenuChoice> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "add" => Some(MenuChoice::Add),
+            "list" => Some(MenuChoice::List),
+            "remove" => Some(MenuChoice::Remove),
+            "save" => Some(MenuChoice::Save),
+            "update" => Some(MenuChoice::Update),
+            "pin" => Some(MenuChoice::Pin),
+            "split" => Some(MenuChoice::Split),
+            "waiting" => Some(MenuChoice::Waiting),
+            "duplicates" => Some(MenuChoice::Duplicates),
+            "todoist" => Some(MenuChoice::Todoist),
+            "risky" => Some(MenuChoice::Risky),
+            "followup" => Some(MenuChoice::FollowUp),
+            "reload" => Some(MenuChoice::Reload),
+            "copy" => Some(MenuChoice::Copy),
+            "import" => Some(MenuChoice::Import),
+            "report" => Some(MenuChoice::Report),
+            "open" => Some(MenuChoice::OpenDir),
+            "exit" => Some(MenuChoice::Exit),
+            _ => None,
+        }
+    }
+
+    // Actions that change tasks or write the data file
+    fn is_mutating(self) -> bool {
+        matches!(
+            self,
+            MenuChoice::Add
+                | MenuChoice::Remove
+                | MenuChoice::Save
+                | MenuChoice::Update
+                | MenuChoice::Pin
+                | MenuChoice::Import
+                | MenuChoice::Split
+                | MenuChoice::Waiting
+                | MenuChoice::FollowUp
+                | MenuChoice::Duplicates
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
 struct MenuLine {
     title: &'static str,
     sub:   &'static str,
     right: &'static str,
+    choice: MenuChoice,
 }
 
 fn draw_divider_line(f: &mut Frame, inner: Rect, y: u16) {
@@ -196,23 +1053,50 @@ fn draw_divider_line(f: &mut Frame, inner: Rect, y: u16) {
     f.render_widget(p, Rect::new(inner.x, y, inner.width, 1));
 }
 
-fn draw_menu(f: &mut Frame, area: Rect, items: &[MenuLine]) {
+fn draw_menu(f: &mut Frame, area: Rect, items: &[MenuLine], read_only: bool, hide_done: bool, notice: Option<&str>) {
+    // A 0/1-cell area can't hold even the border; draw nothing rather than
+    // hand ratatui rows that fall outside the buffer
+    if area.width < 2 || area.height < 2 { return; }
+
     // Outer box
+    let header = if read_only { " header [READ ONLY] " } else { " header " };
     let outer = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .title(Span::styled(
-            " header ",
+            header,
             Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
         ));
     f.render_widget(outer, area);
 
     // Inner content area
     let inner = area.inner(Margin { horizontal: 2, vertical: 1 });
-    if inner.height == 0 { return; }
+    if inner.height == 0 || inner.width == 0 { return; }
     let y_min = inner.y;
     let y_max = inner.y + inner.height - 1; // last valid row
 
+    // On short terminals drop the blank spacers first, then the subtitles,
+    // then the dividers. If the titles alone still don't fit, they're laid
+    // out in columns, so every item title stays visible
+    let n = items.len() as u16;
+    let show_spacer = inner.height >= (n * 4).saturating_sub(2);
+    let show_sub = inner.height >= (n * 3).saturating_sub(1);
+    let show_divider = inner.height >= (n * 2).saturating_sub(1);
+
+    // Gray out actions that read-only mode rejects
+    let styles = |it: &MenuLine| {
+        if read_only && it.choice.is_mutating() {
+            let dim = Style::default().fg(Color::DarkGray);
+            (dim, dim, dim)
+        } else {
+            (
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                Style::default().fg(Color::Magenta),
+                Style::default().fg(Color::Gray),
+            )
+        }
+    };
+
     // Cursor row
     let mut y = y_min;
 
@@ -227,113 +1111,797 @@ fn draw_menu(f: &mut Frame, area: Rect, items: &[MenuLine]) {
         *y = y.saturating_add(1);
     }
 
-    for (i, it) in items.iter().enumerate() {
-        // Title (left) and Right label (same row)
-        if y <= y_max {
-            let row = Rect::new(inner.x, y, inner.width, 1);
+    if n > inner.height {
+        let rows = inner.height as usize;
+        let cols = items.len().div_ceil(rows) as u16;
+        let col_w = inner.width / cols;
+        for (i, it) in items.iter().enumerate() {
+            let (col, row) = ((i / rows) as u16, (i % rows) as u16);
+            let cell = Rect::new(inner.x + col * col_w, inner.y + row, col_w, 1);
+            f.render_widget(Paragraph::new(Span::styled(it.title, styles(it).0)), cell);
+        }
+    } else {
+        for (i, it) in items.iter().enumerate() {
+            let (title_style, right_style, sub_style) = styles(it);
 
-            let title = Paragraph::new(Line::from(Span::styled(
-                it.title,
-                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-            )))
-            .alignment(Alignment::Left);
+            // Title (left) and Right label (same row)
+            if y <= y_max {
+                let row = Rect::new(inner.x, y, inner.width, 1);
 
-            let right = Paragraph::new(Line::from(Span::styled(
-                it.right,
-                Style::default().fg(Color::Magenta),
-            )))
-            .alignment(Alignment::Right);
-
-            // Render both on the same row
-            f.render_widget(title, row);
-            f.render_widget(right, row);
-        }
-        y = y.saturating_add(1);
-
-        // Subtitle line
-        let sub = Paragraph::new(Line::from(Span::styled(
-            it.sub,
-            Style::default().fg(Color::Gray),
-        )));
-        render_line(f, inner, &mut y, y_max, sub, Alignment::Left);
-
-        // Divider between items
-        if i < items.len() - 1 {
-            // optional blank spacer
-            render_line(f, inner, &mut y, y_max, Paragraph::new(""), Alignment::Left);
-            draw_divider_line(f, inner, y);
+                let title = Paragraph::new(Line::from(Span::styled(it.title, title_style)))
+                    .alignment(Alignment::Left);
+
+                let right = Paragraph::new(Line::from(Span::styled(it.right, right_style)))
+                .alignment(Alignment::Right);
+
+                // Render both on the same row
+                f.render_widget(title, row);
+                f.render_widget(right, row);
+            }
             y = y.saturating_add(1);
-        }
 
-        // Stop if we ran out of vertical space
-        if y > y_max { break; }
-    }
+            // Subtitle line
+            if show_sub {
+                let sub = Paragraph::new(Line::from(Span::styled(it.sub, sub_style)));
+                render_line(f, inner, &mut y, y_max, sub, Alignment::Left);
+            }
 
-    // Footer hint on the **last valid row** of the outer area
+            // Divider between items
+            if i < items.len() - 1 && show_divider {
+                // optional blank spacer
+                if show_spacer {
+                    render_line(f, inner, &mut y, y_max, Paragraph::new(""), Alignment::Left);
+                }
+                draw_divider_line(f, inner, y);
+                y = y.saturating_add(1);
+            }
+
+            // Stop if we ran out of vertical space
+            if y > y_max { break; }
+        }
+    }
+
+    // Footer hint on the **last valid row** of the outer area
     if area.height > 0 {
         let footer_y = area.y + area.height - 1;
-        let hint = Paragraph::new(Line::from(vec![
+        if let Some(msg) = notice {
+            let p = Paragraph::new(Span::styled(msg, Style::default().fg(Color::Yellow)))
+                .alignment(Alignment::Center);
+            f.render_widget(p, Rect::new(area.x, footer_y, area.width, 1));
+            return;
+        }
+        // Hotkeys are read off the item titles ("p) Pin task"); the digits
+        // collapse to a range when none are hidden
+        let first_chars: Vec<char> = items.iter().filter_map(|it| it.title.chars().next()).collect();
+        let mut digits: Vec<char> = first_chars.iter().copied().filter(|c| c.is_ascii_digit()).collect();
+        digits.sort_unstable();
+        let contiguous = digits.windows(2).all(|w| w[1] as u8 == w[0] as u8 + 1);
+        let mut keys: Vec<String> = match digits.as_slice() {
+            [] => Vec::new(),
+            [d] => vec![d.to_string()],
+            [first, .., last] if contiguous => vec![format!("{first}-{last}")],
+            ds => ds.iter().map(|d| d.to_string()).collect(),
+        };
+        keys.extend(first_chars.iter().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_string()));
+        let key = |k: String| Span::styled(k, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+        let mut spans = vec![
             Span::raw("Press "),
-            Span::styled("1-6", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            key(keys.join(" ")),
             Span::raw(" to select • "),
-            Span::styled("q", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
-            Span::raw(" to quit"),
-        ]))
-        .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::Gray));
+            key("q".into()),
+            Span::raw(" to quit • "),
+            key("h".into()),
+            Span::raw(if hide_done { " done: hidden" } else { " done: shown" }),
+        ];
+        // The keys are on the item titles too; drop them before the line overflows
+        if Line::from(spans.clone()).width() > area.width as usize {
+            spans.drain(..3);
+        }
+        let hint = Paragraph::new(Line::from(spans))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Gray));
         f.render_widget(hint, Rect::new(area.x, footer_y, area.width, 1));
     }
 }
 
 
-fn run_menu_tui() -> io::Result<Option<MenuChoice>> {
-    let items = [
-        MenuLine { title: "1) Add task",        sub: "Create a new task (auto-ID)",                  right: "default" },
-        MenuLine { title: "2) List tasks",      sub: "Pretty table with colored status",             right: "view"    },
-        MenuLine { title: "3) Remove task",     sub: "Delete by ID",                                 right: "danger"  },
-        MenuLine { title: "4) Save (JSON)",     sub: "Write tasks.json (pretty JSON)",               right: "persist" },
-        MenuLine { title: "5) Update status",   sub: "Change Todo/InProgress/Done by ID",            right: "edit"    },
-        MenuLine { title: "6) Exit",            sub: "Close program",                                right: "quit"    },
-    ];
+const MENU_ITEMS: &[MenuLine] = &[
+    MenuLine { title: "1) Add task",        sub: "Create a new task (auto-ID)",                  right: "default", choice: MenuChoice::Add },
+    MenuLine { title: "2) List tasks",      sub: "Pretty table with colored status",             right: "view",    choice: MenuChoice::List },
+    MenuLine { title: "3) Remove task",     sub: "Delete by ID",                                 right: "danger",  choice: MenuChoice::Remove },
+    MenuLine { title: "4) Save (JSON)",     sub: "Write the data file (pretty JSON)",            right: "persist", choice: MenuChoice::Save },
+    MenuLine { title: "5) Update status",   sub: "Change Todo/InProgress/Blocked/Done by ID",    right: "edit",    choice: MenuChoice::Update },
+    MenuLine { title: "n) Done + next",     sub: "Finish a task and add its follow-up",          right: "edit",    choice: MenuChoice::FollowUp },
+    MenuLine { title: "p) Pin task",        sub: "Pin/unpin a task to the top of the list",      right: "edit",    choice: MenuChoice::Pin },
+    MenuLine { title: "s) Split task",      sub: "Turn one task into two (new ID for the 2nd)",  right: "edit",    choice: MenuChoice::Split },
+    MenuLine { title: "w) Waiting on",      sub: "Note who/what a task waits on, or clear it",   right: "edit",    choice: MenuChoice::Waiting },
+    MenuLine { title: "k) Risky tasks",     sub: "Open tasks flagged High or Medium risk",       right: "view",    choice: MenuChoice::Risky },
+    MenuLine { title: "d) Duplicates",      sub: "Find look-alike tasks and merge them",         right: "edit",    choice: MenuChoice::Duplicates },
+    MenuLine { title: "y) Copy as JSON",    sub: "Put one task on the clipboard as JSON",        right: "export",  choice: MenuChoice::Copy },
+    MenuLine { title: "i) Import",          sub: "Merge *.json task files, or a .txt of lines",  right: "import",  choice: MenuChoice::Import },
+    MenuLine { title: "r) Export report",   sub: "Write report.txt (plain text, by status)",     right: "export",  choice: MenuChoice::Report },
+    MenuLine { title: "t) Export Todoist",  sub: "Write todoist.json to import into Todoist",   right: "export",  choice: MenuChoice::Todoist },
+    MenuLine { title: "R) Reload",          sub: "Re-read the data file after hand edits",       right: "persist", choice: MenuChoice::Reload },
+    MenuLine { title: "o) Open folder",     sub: "Data folder in the file manager",              right: "view",    choice: MenuChoice::OpenDir },
+    MenuLine { title: "6) Exit",            sub: "Close program",                                right: "quit",    choice: MenuChoice::Exit },
+];
+
+// The menu entries named in config, in that order
+fn menu_items(names: &[String]) -> Vec<MenuLine> {
+    names.iter()
+        .filter_map(|name| MenuChoice::from_name(name))
+        .filter_map(|choice| MENU_ITEMS.iter().find(|it| it.choice == choice).copied())
+        .collect()
+}
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+// Hotkey -> action, shared by the TUI and the plain-text menu
+fn choice_for_key(c: char) -> Option<MenuChoice> {
+    match c {
+        '1' => Some(MenuChoice::Add),
+        '2' => Some(MenuChoice::List),
+        '3' => Some(MenuChoice::Remove),
+        '4' => Some(MenuChoice::Save),
+        '5' => Some(MenuChoice::Update),
+        '6' => Some(MenuChoice::Exit),
+        'n' => Some(MenuChoice::FollowUp),
+        'p' => Some(MenuChoice::Pin),
+        's' => Some(MenuChoice::Split),
+        'w' => Some(MenuChoice::Waiting),
+        'd' => Some(MenuChoice::Duplicates),
+        'k' => Some(MenuChoice::Risky),
+        'y' => Some(MenuChoice::Copy),
+        'i' => Some(MenuChoice::Import),
+        'r' => Some(MenuChoice::Report),
+        't' => Some(MenuChoice::Todoist),
+        'R' => Some(MenuChoice::Reload),
+        'o' => Some(MenuChoice::OpenDir),
+        _ => None,
+    }
+}
 
-    let choice = loop {
+// How a visit to the menu ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum MenuExit {
+    Chose(MenuChoice),
+    // `q` / Ctrl+C
+    Quit,
+    // stdin is exhausted (plain menu fed from a pipe); nothing more can be asked
+    InputClosed,
+}
+
+// `h` flips `hide_done` in place and keeps the menu open. With `flash`, the
+// first frame is drawn inverted as feedback that the last action worked.
+// `banner` replaces the footer hint until the first key press. The last
+// frame is kept as the backdrop for the chosen action's dialogs.
+fn run_menu_tui(overlay: &mut Overlay, items: &[MenuLine], read_only: bool, hide_done: &mut bool, flash: bool, banner: Option<&str>) -> Result<MenuExit, AppError> {
+    let terminal = &mut overlay.terminal;
+    // Whatever the last action printed was shown (or had its chance)
+    overlay.lines.clear();
+
+    if flash {
         terminal.draw(|f| {
+            let area = f.area();
+            draw_menu(f, area, items, read_only, *hide_done, banner);
+            f.buffer_mut().set_style(area, Style::default().add_modifier(Modifier::REVERSED));
+        })?;
+        std::thread::sleep(std::time::Duration::from_millis(120));
+    }
+
+    // Shown in place of the footer hint until the next key press
+    let mut notice: Option<&str> = banner;
+
+    let choice = loop {
+        let frame = terminal.draw(|f| {
             let area = f.area();
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Percentage(100)].as_ref())
                 .split(area);
-            draw_menu(f, chunks[0], &items);
+            draw_menu(f, chunks[0], items, read_only, *hide_done, notice);
+        })?;
+        overlay.backdrop.clone_from(frame.buffer);
+
+        if crossterm::event::poll(std::time::Duration::from_millis(50))?
+            && let Event::Key(k) = event::read()?
+        {
+            notice = None;
+            let pressed = match k.code {
+                KeyCode::Char('q') => break MenuExit::Quit,
+                // Raw mode turns Ctrl+C into a key press; treat it like q
+                KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => break MenuExit::Quit,
+                KeyCode::Char('h') => {
+                    *hide_done = !*hide_done;
+                    None
+                }
+                KeyCode::Esc => Some(MenuChoice::Exit),
+                // Keys of entries hidden by the `menu` config do nothing
+                KeyCode::Char(c) => choice_for_key(c).filter(|ch| items.iter().any(|it| it.choice == *ch)),
+                _ => None,
+            };
+            match pressed {
+                Some(choice) if read_only && choice.is_mutating() => {
+                    notice = Some("Read-only mode: that action is disabled");
+                }
+                Some(choice) => break MenuExit::Chose(choice),
+                None => {}
+            }
+        }
+    };
+
+    Ok(choice)
+}
+
+// Numbered text menu read from stdin, for when raw mode / the alternate
+// screen aren't available (pipes, CI, dumb terminals). Same keys as the TUI.
+fn run_menu_plain(items: &[MenuLine], read_only: bool, hide_done: &mut bool) -> Result<MenuExit, AppError> {
+    loop {
+        println!();
+        if read_only {
+            println!("{}", "[READ ONLY]".magenta().bold());
+        }
+        for it in items {
+            let line = format!("{:<20} {}", it.title, it.sub);
+            if read_only && it.choice.is_mutating() {
+                println!("{}", line.dimmed());
+            } else {
+                println!("{line}");
+            }
+        }
+        let done = if *hide_done { "hidden" } else { "shown" };
+        print!("Choose (q to quit, h to toggle done tasks [{done}]): ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            println!();
+            return Ok(MenuExit::InputClosed);
+        }
+        let input = input.trim();
+        if input == "q" {
+            return Ok(MenuExit::Quit);
+        }
+        if input == "h" {
+            *hide_done = !*hide_done;
+            continue;
+        }
+        let mut chars = input.chars();
+        let pressed = match (chars.next(), chars.next()) {
+            (Some(c), None) => choice_for_key(c).filter(|ch| items.iter().any(|it| it.choice == *ch)),
+            _ => None,
+        };
+        match pressed {
+            Some(choice) if read_only && choice.is_mutating() => {
+                println!("Read-only mode: that action is disabled");
+            }
+            Some(choice) => return Ok(MenuExit::Chose(choice)),
+            None => println!("Unknown choice: {input}"),
+        }
+    }
+}
+
+type Tui = Terminal<CrosstermBackend<io::Stdout>>;
+
+// What a full-screen view switches on while it runs and off afterwards
+trait Screen {
+    fn enter(&mut self) -> io::Result<()>;
+    // Best effort: runs while unwinding or returning an error
+    fn leave(&mut self);
+}
+
+impl Screen for Tui {
+    fn enter(&mut self) -> io::Result<()> {
+        enable_raw_mode()?;
+        execute!(self.backend_mut(), EnterAlternateScreen)?;
+        // ratatui only draws what changed; start it from a known blank screen
+        self.clear()
+    }
+
+    fn leave(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.backend_mut(), LeaveAlternateScreen);
+        let _ = self.show_cursor();
+    }
+}
+
+// Raw mode + alternate screen for as long as it lives. Dropping it restores
+// the terminal, so `?` and early returns can't leave it broken. `main` holds
+// one for the whole session: the menu and every dialog share the screen.
+struct TerminalGuard<'a, S: Screen> {
+    screen: &'a mut S,
+}
+
+impl<'a, S: Screen> TerminalGuard<'a, S> {
+    fn enter(screen: &'a mut S) -> Result<TerminalGuard<'a, S>, AppError> {
+        if let Err(e) = screen.enter() {
+            // No guard to drop yet; undo whatever part of it happened
+            screen.leave();
+            return Err(e.into());
+        }
+        Ok(TerminalGuard { screen })
+    }
+}
+
+impl<S: Screen> Drop for TerminalGuard<'_, S> {
+    fn drop(&mut self) {
+        self.screen.leave();
+    }
+}
+
+// First-run welcome screen. Returns whether the user wants a sample task;
+// Esc (or `n`) skips it.
+fn run_onboarding_tui(terminal: &mut Tui, data_file: &str) -> Result<bool, AppError> {
+    let key = |k: &'static str| Span::styled(k, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    let text = vec![
+        Line::from(Span::styled("Welcome to To-Do!", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from("Everything happens from the main menu. Press an item's key to run it:"),
+        Line::from(vec![Span::raw("  "), key("1"), Span::raw(" add a task, "), key("2"), Span::raw(" list them, "), key("5"), Span::raw(" change a status")]),
+        Line::from(vec![Span::raw("  "), key("p"), Span::raw(" pin, "), key("s"), Span::raw(" split, "), key("r"), Span::raw(" export a report")]),
+        Line::from(vec![Span::raw("  "), key("q"), Span::raw(" or "), key("Esc"), Span::raw(" to quit")]),
+        Line::from(""),
+        Line::from(format!("Changes are saved to {data_file} as you go.")),
+        Line::from(""),
+        Line::from(vec![Span::raw("Create a sample task to try things out? "), key("y"), Span::raw(" / "), key("n")]),
+    ];
+
+    let create = loop {
+        terminal.draw(|f| {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(Span::styled(" welcome ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)));
+            let p = Paragraph::new(text.clone()).block(block).wrap(Wrap { trim: false });
+            f.render_widget(p, f.area());
         })?;
 
-        if crossterm::event::poll(std::time::Duration::from_millis(50))? {
-            if let Event::Key(k) = event::read()? {
-                match k.code {
-                    KeyCode::Char('1') => break Some(MenuChoice::Add),
-                    KeyCode::Char('2') => break Some(MenuChoice::List),
-                    KeyCode::Char('3') => break Some(MenuChoice::Remove),
-                    KeyCode::Char('4') => break Some(MenuChoice::Save),
-                    KeyCode::Char('5') => break Some(MenuChoice::Update),
-                    KeyCode::Char('6') | KeyCode::Esc => break Some(MenuChoice::Exit),
-                    KeyCode::Char('q') => break None,
-                    _ => {}
+        if crossterm::event::poll(std::time::Duration::from_millis(50))?
+            && let Event::Key(k) = event::read()?
+        {
+            match k.code {
+                KeyCode::Char('y') | KeyCode::Enter => break true,
+                KeyCode::Char('n') | KeyCode::Esc => break false,
+                KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => break false,
+                _ => {}
+            }
+        }
+    };
+
+    Ok(create)
+}
+
+// =========================
+// Dialogs over the TUI menu
+// =========================
+
+// While the full-screen menu is in use its terminal lives here rather than
+// in main, so prompts and `say!` from anywhere can draw in a box over the
+// menu. Actions then never leave the alternate screen; switching back and
+// forth for every action is what made the screen flicker. None with the
+// plain menu.
+static OVERLAY: Mutex<Option<Overlay>> = Mutex::new(None);
+
+struct Overlay {
+    terminal: Tui,
+    // The last menu frame, shown dimmed behind the box
+    backdrop: Buffer,
+    // What the current action has printed, one entry per line
+    lines: Vec<String>,
+}
+
+// Runs `f` on the overlay, or returns None if there isn't one
+fn with_overlay<R>(f: impl FnOnce(&mut Overlay) -> R) -> Option<R> {
+    OVERLAY.lock().unwrap_or_else(|e| e.into_inner()).as_mut().map(f)
+}
+
+// The terminal in OVERLAY, for `TerminalGuard`
+struct OverlayScreen;
+
+impl Screen for OverlayScreen {
+    fn enter(&mut self) -> io::Result<()> {
+        with_overlay(|o| o.terminal.enter()).unwrap_or(Ok(()))
+    }
+
+    fn leave(&mut self) {
+        with_overlay(|o| o.terminal.leave());
+    }
+}
+
+// The question at the bottom of the box
+enum Ask<'a> {
+    // `cursor` counts characters, not bytes
+    Text { prompt: &'a str, value: &'a str, cursor: usize, error: Option<&'a str> },
+    Select { prompt: &'a str, items: &'a [Line<'a>], selected: usize },
+    Confirm { prompt: &'a str, default: bool },
+    // "Press Enter", with the transcript scrolled by (rows, columns)
+    Pause { scroll: (u16, u16) },
+}
+
+// The dimmed menu, then a box with the transcript on top and `ask` at the
+// bottom. Questions follow the end of the transcript; a pause shows it from
+// `scroll`. Returns how far the transcript can scroll (rows, columns).
+fn draw_overlay(f: &mut Frame, backdrop: &Buffer, lines: &[String], ask: &Ask) -> (u16, u16) {
+    let area = f.area();
+    let buf = f.buffer_mut();
+    let shared = area.intersection(backdrop.area);
+    for y in shared.top()..shared.bottom() {
+        for x in shared.left()..shared.right() {
+            if let (Some(to), Some(from)) = (buf.cell_mut((x, y)), backdrop.cell((x, y))) {
+                *to = from.clone();
+            }
+        }
+    }
+    buf.set_style(area, Style::default().add_modifier(Modifier::DIM));
+
+    // Keep a margin of menu around the box when there's room for one
+    let dialog = if area.width >= 20 && area.height >= 10 { area.inner(Margin { horizontal: 2, vertical: 1 }) } else { area };
+    if dialog.width < 3 || dialog.height < 3 { return (0, 0); }
+    f.render_widget(Clear, dialog);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(dialog);
+    f.render_widget(block, dialog);
+
+    let ask_height = match ask {
+        Ask::Text { error, .. } => 2 + u16::from(error.is_some()),
+        Ask::Select { items, .. } => u16::try_from(items.len()).unwrap_or(u16::MAX).saturating_add(1),
+        Ask::Confirm { .. } | Ask::Pause { .. } => 1,
+    };
+    let mut style = Style::default();
+    let text: Vec<Line> = lines.iter().map(|l| ansi_line(l, &mut style)).collect();
+    let rows = u16::try_from(text.len()).unwrap_or(u16::MAX);
+    // The question goes right under the transcript, like it would in a
+    // terminal, and the transcript gives way when there isn't room for both
+    let ask_height = ask_height.min(inner.height);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(rows.min(inner.height - ask_height)), Constraint::Length(ask_height), Constraint::Min(0)].as_ref())
+        .split(inner);
+    let cols = u16::try_from(text.iter().map(Line::width).max().unwrap_or(0)).unwrap_or(u16::MAX);
+    let max_scroll = (rows.saturating_sub(chunks[0].height), cols.saturating_sub(chunks[0].width));
+    let scroll = match ask {
+        Ask::Pause { scroll } => (scroll.0.min(max_scroll.0), scroll.1.min(max_scroll.1)),
+        _ => (max_scroll.0, 0),
+    };
+    f.render_widget(Paragraph::new(text).scroll(scroll), chunks[0]);
+
+    let question = chunks[1];
+    let prompt_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let hint_style = Style::default().fg(Color::DarkGray);
+    match ask {
+        Ask::Text { prompt, value, cursor, error } => {
+            let mut rows = vec![Line::from(Span::styled(*prompt, prompt_style)), Line::from(format!("› {value}"))];
+            if let Some(error) = error {
+                rows.push(Line::from(Span::styled(*error, Style::default().fg(Color::Red))));
+            }
+            f.render_widget(Paragraph::new(rows), question);
+            if question.height >= 2 {
+                let before: String = value.chars().take(*cursor).collect();
+                let x = question.x.saturating_add(2).saturating_add(u16::try_from(Span::raw(before).width()).unwrap_or(u16::MAX));
+                f.set_cursor_position((x.min(question.right().saturating_sub(1)), question.y + 1));
+            }
+        }
+        Ask::Select { prompt, items, selected } => {
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                .split(question);
+            f.render_widget(Paragraph::new(Span::styled(*prompt, prompt_style)), parts[0]);
+            let list = List::new(items.iter().cloned())
+                .highlight_symbol("› ")
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default().with_selected(Some(*selected));
+            f.render_stateful_widget(list, parts[1], &mut state);
+        }
+        Ask::Confirm { prompt, default } => {
+            let hint = if *default { " [Y/n]" } else { " [y/N]" };
+            f.render_widget(Paragraph::new(Line::from(vec![Span::styled(*prompt, prompt_style), Span::styled(hint, hint_style)])), question);
+        }
+        Ask::Pause { .. } => {
+            let hint = if max_scroll == (0, 0) { "Press Enter to continue" } else { "↑↓←→ to scroll · Enter to continue" };
+            f.render_widget(Paragraph::new(Span::styled(hint, hint_style)), question);
+        }
+    }
+    max_scroll
+}
+
+// One line of `colored` output as styled spans: SGR escape codes become
+// styles and any other escape sequence is dropped. `style` carries over
+// from line to line, as a colored string can span several.
+fn ansi_line(s: &str, style: &mut Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('\x1b') {
+        if start > 0 {
+            spans.push(Span::styled(rest[..start].to_string(), *style));
+        }
+        let Some(seq) = rest[start + 1..].strip_prefix('[') else {
+            rest = &rest[start + 1..];
+            continue;
+        };
+        // Parameters, then one final byte in @..~
+        let Some(end) = seq.find(|c: char| ('@'..='~').contains(&c)) else {
+            rest = "";
+            break;
+        };
+        if seq[end..].starts_with('m') {
+            *style = apply_sgr(*style, &seq[..end]);
+        }
+        rest = &seq[end + 1..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), *style));
+    }
+    Line::from(spans)
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    const COLORS: [Color; 8] = [Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::Gray];
+    const BRIGHT: [Color; 8] = [
+        Color::DarkGray, Color::LightRed, Color::LightGreen, Color::LightYellow,
+        Color::LightBlue, Color::LightMagenta, Color::LightCyan, Color::White,
+    ];
+    // An empty parameter (as in "\x1b[m") means 0, reset
+    let mut codes = params.split(';').map(|c| c.parse::<u8>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            30..=37 => style.fg(COLORS[usize::from(code - 30)]),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(COLORS[usize::from(code - 40)]),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(BRIGHT[usize::from(code - 90)]),
+            100..=107 => style.bg(BRIGHT[usize::from(code - 100)]),
+            // 256 colors (5;n) or true color (2;r;g;b)
+            38 | 48 => {
+                let color = match codes.next() {
+                    Some(5) => codes.next().map(Color::Indexed),
+                    Some(2) => match (codes.next(), codes.next(), codes.next()) {
+                        (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match color {
+                    Some(color) if code == 38 => style.fg(color),
+                    Some(color) => style.bg(color),
+                    None => style,
+                }
+            }
+            _ => style,
+        };
+    }
+    style
+}
+
+// Next key press, or None for any other event (a resize, say) after which
+// the caller just redraws. Raw mode turns Ctrl+C into a key; in a dialog it
+// does what it does outside the TUI.
+fn read_key() -> io::Result<Option<KeyEvent>> {
+    match event::read()? {
+        Event::Key(k) if k.kind == KeyEventKind::Press => {
+            if k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL) {
+                exit_on_interrupt();
+            }
+            Ok(Some(k))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Byte offset of the `n`th character of `s` (its length past the end)
+fn char_offset(s: &str, n: usize) -> usize {
+    s.char_indices().nth(n).map_or(s.len(), |(i, _)| i)
+}
+
+// The prompt helpers' TUI versions. A terminal error counts as cancelling,
+// like it does for dialoguer.
+impl Overlay {
+    fn draw(&mut self, ask: &Ask) -> io::Result<(u16, u16)> {
+        let mut max_scroll = (0, 0);
+        self.terminal.draw(|f| max_scroll = draw_overlay(f, &self.backdrop, &self.lines, ask))?;
+        Ok(max_scroll)
+    }
+
+    // Leaves the answered question in the transcript, as dialoguer leaves it
+    // on the screen
+    fn answered(&mut self, prompt: &str, answer: &str) {
+        self.lines.push(format!("{} {} · {answer}", "✔".green(), prompt.bold()));
+    }
+
+    // Enter submits, Esc cancels; ←/→/Home/End move the cursor
+    fn text(&mut self, prompt: &str, initial: &str, check: &dyn Fn(&str) -> Result<(), &'static str>) -> Option<String> {
+        let mut value = initial.to_string();
+        let mut cursor = value.chars().count();
+        let mut error = None;
+        loop {
+            self.draw(&Ask::Text { prompt, value: &value, cursor, error }).ok()?;
+            let Some(key) = read_key().ok()? else { continue };
+            match key.code {
+                KeyCode::Enter => match check(&value) {
+                    Ok(()) => {
+                        self.answered(prompt, &value);
+                        return Some(value);
+                    }
+                    Err(msg) => error = Some(msg),
+                },
+                KeyCode::Esc => return None,
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    value.insert(char_offset(&value, cursor), c);
+                    cursor += 1;
+                }
+                KeyCode::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    value.remove(char_offset(&value, cursor));
+                }
+                KeyCode::Delete if cursor < value.chars().count() => {
+                    value.remove(char_offset(&value, cursor));
+                }
+                KeyCode::Left => cursor = cursor.saturating_sub(1),
+                KeyCode::Right => cursor = (cursor + 1).min(value.chars().count()),
+                KeyCode::Home => cursor = 0,
+                KeyCode::End => cursor = value.chars().count(),
+                _ => {}
+            }
+        }
+    }
+
+    // ↑/↓ (or k/j), PgUp/PgDn, Home/End to move, Enter to pick, Esc cancels
+    fn select(&mut self, prompt: &str, items: &[String], default: usize) -> Option<usize> {
+        let lines: Vec<Line> = items.iter().map(|s| ansi_line(s, &mut Style::default())).collect();
+        let last = items.len().saturating_sub(1);
+        let mut selected = default.min(last);
+        loop {
+            self.draw(&Ask::Select { prompt, items: &lines, selected }).ok()?;
+            let Some(key) = read_key().ok()? else { continue };
+            selected = match key.code {
+                KeyCode::Enter if !items.is_empty() => {
+                    self.answered(prompt, &items[selected]);
+                    return Some(selected);
                 }
+                KeyCode::Esc => return None,
+                KeyCode::Up | KeyCode::Char('k') => selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => (selected + 1).min(last),
+                KeyCode::PageUp => selected.saturating_sub(10),
+                KeyCode::PageDown => (selected + 10).min(last),
+                KeyCode::Home => 0,
+                KeyCode::End => last,
+                _ => selected,
+            };
+        }
+    }
+
+    // y / n, Enter for the default; Esc answers no
+    fn confirm(&mut self, prompt: &str, default: bool) -> bool {
+        loop {
+            if self.draw(&Ask::Confirm { prompt, default }).is_err() {
+                return false;
             }
+            let answer = match read_key() {
+                Err(_) => return false,
+                Ok(None) => continue,
+                Ok(Some(key)) => match key.code {
+                    KeyCode::Char('y' | 'Y') => true,
+                    KeyCode::Char('n' | 'N') | KeyCode::Esc => false,
+                    KeyCode::Enter => default,
+                    _ => continue,
+                },
+            };
+            self.answered(prompt, if answer { "yes" } else { "no" });
+            return answer;
+        }
+    }
+
+    // Holds what the action printed on screen until Enter (or Esc/q/space),
+    // with the arrow keys scrolling it. Returns at once if it printed nothing.
+    fn pause(&mut self) {
+        let mut scroll = (0, 0);
+        while !self.lines.is_empty() {
+            let Ok(max) = self.draw(&Ask::Pause { scroll }) else { break };
+            let Ok(key) = read_key() else { break };
+            let Some(key) = key else { continue };
+            scroll = match key.code {
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Char(' ' | 'q') => break,
+                KeyCode::Up => (scroll.0.saturating_sub(1), scroll.1),
+                KeyCode::Down => (scroll.0.saturating_add(1), scroll.1),
+                KeyCode::PageUp => (scroll.0.saturating_sub(10), scroll.1),
+                KeyCode::PageDown => (scroll.0.saturating_add(10), scroll.1),
+                KeyCode::Left => (scroll.0, scroll.1.saturating_sub(8)),
+                KeyCode::Right => (scroll.0, scroll.1.saturating_add(8)),
+                KeyCode::Home => (0, 0),
+                KeyCode::End => (max.0, 0),
+                _ => scroll,
+            };
+            scroll = (scroll.0.min(max.0), scroll.1.min(max.1));
         }
+        self.lines.clear();
+    }
+}
+
+// Appends a timestamped line to the action log. This is an audit trail only,
+// so a failure to write it is reported but never stops the action.
+fn log_action(path: &std::path::Path, msg: &str) {
+    let line = format!("{}  {msg}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"));
+    let res = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(line.as_bytes()));
+    if let Err(e) = res {
+        say_err!("Could not write {}: {e}", path.display());
+    }
+}
+
+// Folder holding the data file (and the exports written next to it)
+fn data_dir(data_file: &str) -> std::path::PathBuf {
+    let path = std::fs::canonicalize(data_file)
+        .or_else(|_| std::env::current_dir().map(|d| d.join(data_file)))
+        .unwrap_or_else(|_| data_file.into());
+    path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| ".".into())
+}
+
+// Where the app's other files (exports, log, state) go: next to the data
+// file, so each task list keeps its own wherever the app is started from
+fn beside_data_file(config: &Config, name: &str) -> std::path::PathBuf {
+    data_dir(&config.data_file).join(name)
+}
+
+// Opens `dir` in the OS file manager
+fn open_in_file_manager(dir: &std::path::Path) -> io::Result<()> {
+    let opener = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
     };
+    // Its chatter on stdout/stderr would land on top of the TUI
+    std::process::Command::new(opener)
+        .arg(dir)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+// Set once the title has been changed, so exiting knows to put it back
+static TITLE_CHANGED: AtomicBool = AtomicBool::new(false);
 
-    Ok(choice)
+// Shows how many tasks are still open in the terminal (or console) title.
+// The first call pushes the current title onto the terminal's title stack
+// (XTWINOPS 22) where there is one.
+fn update_terminal_title(tasks: &[Task]) {
+    let mut out = io::stdout();
+    if !TITLE_CHANGED.swap(true, Ordering::Relaxed) && !cfg!(windows) {
+        let _ = write!(out, "\x1b[22;0t");
+    }
+    let open = tasks.iter().filter(|t| t.status != TaskStatus::Done).count();
+    let _ = execute!(out, SetTitle(format!("To-Do ({open} open)")));
+}
+
+// Undoes `update_terminal_title` on the way out: clears the title, then pops
+// the saved one (XTWINOPS 23). Terminals without a title stack are left
+// with an empty title rather than a stale count.
+fn restore_terminal_title() {
+    if !TITLE_CHANGED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let mut out = io::stdout();
+    let _ = execute!(out, SetTitle(""));
+    if !cfg!(windows) {
+        let _ = write!(out, "\x1b[23;0t");
+        let _ = out.flush();
+    }
 }
 
 #[cfg(windows)]
@@ -371,16 +1939,17 @@ fn maybe_relaunch_in_terminal() -> bool {
     use std::{env, fs, path::PathBuf, process::Command};
 
     if env::var("RUN_IN_TERM").is_ok() {
+        log::debug!("RUN_IN_TERM set; not relaunching");
         return false; // already relaunched
     }
 
     // 1) Current exe
     let exe = match env::current_exe() {
         Ok(p) => p,
-        Err(e) => { eprintln!("current_exe() failed: {e}"); return false; }
+        Err(e) => { log::warn!("current_exe() failed: {e}"); return false; }
     };
     if !exe.exists() {
-        eprintln!("Executable not found: {}", exe.display());
+        log::warn!("Executable not found: {}", exe.display());
         return false;
     }
 
@@ -389,16 +1958,22 @@ fn maybe_relaunch_in_terminal() -> bool {
     ps1.push("launch_my_app.ps1");
 
     // Use double quotes; PowerShell treats this as a literal path invocation.
-    let script = format!("& \"{}\"\n", exe.display());
+    // Forward our own arguments as single-quoted literals ('' escapes a quote).
+    let forwarded: Vec<String> = env::args()
+        .skip(1)
+        .map(|a| format!("'{}'", a.replace('\'', "''")))
+        .collect();
+    let script = format!("& \"{}\" {}\n", exe.display(), forwarded.join(" "));
     if let Err(e) = fs::write(&ps1, &script) {
-        eprintln!("Failed to write temp ps1: {e}");
+        log::warn!("Failed to write temp ps1: {e}");
         return false;
     }
     if !ps1.exists() {
-        eprintln!("Temp ps1 not found: {}", ps1.display());
+        log::warn!("Temp ps1 not found: {}", ps1.display());
         return false;
     }
-    eprintln!("Temp script at: {}", ps1.display()); // debug print
+    log::debug!("Temp script at: {}", ps1.display());
+    log::debug!("Script: {}", script.trim_end());
 
     // 3) Full path to Windows PowerShell (avoid PATH issues)
     let sysroot = env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
@@ -418,154 +1993,1694 @@ fn maybe_relaunch_in_terminal() -> bool {
         .spawn();
 
     match spawn_res {
-        Ok(_) => true, // spawned → caller should exit
+        Ok(_) => {
+            log::debug!("Relaunched in {ps_exe}");
+            true // spawned → caller should exit
+        }
         Err(e) => {
-            eprintln!("Failed to start PowerShell: {e}");
+            log::warn!("Failed to start PowerShell: {e}");
             false
         }
     }
 }
 
 const TASKS_FILE: &str = "tasks.json";
+const CONFIG_FILE: &str = "config.json";
+const REPORT_FILE: &str = "report.txt";
+const TODOIST_FILE: &str = "todoist.json";
+const LOG_FILE: &str = "todo.log";
+const STATE_FILE: &str = "state.json";
+
+// ======================
+// Config (config.json)
+// ======================
 
-fn load_tasks() -> Vec<Task> {
-    match std::fs::read_to_string(TASKS_FILE) {
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct Config {
+    /// Indent used when writing JSON files: two spaces, four spaces or a tab.
+    json_indent: String,
+    /// Show the open task count in the terminal title. Some multiplexers override titles.
+    set_terminal_title: bool,
+    /// First ID handed out when starting a fresh, empty list.
+    start_id: Option<u32>,
+    /// Trim and collapse whitespace in new titles. Off keeps input exactly as typed.
+    normalize_titles: bool,
+    /// With `normalize_titles`, also capitalize the first letter of each word.
+    title_case: bool,
+    /// Task file. Overridden by TODO_FILE, then --file.
+    data_file: String,
+    /// Status preselected when adding. Overridden by TODO_DEFAULT_STATUS, then --default-status.
+    default_status: TaskStatus,
+    /// Use `[ ]` / `[~]` / `[x]` instead of Unicode checkboxes in selectors.
+    ascii_symbols: bool,
+    /// Menu actions to show, in order. Defaults to all of them.
+    menu: Vec<String>,
+    /// Shown by List when there are no tasks, instead of the default panel.
+    empty_message: Option<String>,
+    /// Default answer to "Add another?" after adding a task.
+    quick_capture: bool,
+    /// Stored instead of an empty description, e.g. "-". Unset keeps it empty.
+    empty_description_placeholder: Option<String>,
+    /// Warn before the list grows past this many tasks. Unset = no limit.
+    max_tasks: Option<usize>,
+    /// At `max_tasks`, refuse new tasks instead of asking.
+    block_at_max_tasks: bool,
+    /// Ask before marking a task Done (Update, Done + next).
+    confirm_complete: bool,
+    /// Flash the menu once after a change is saved (visual feedback).
+    flash_on_action: bool,
+    /// Start with Done tasks hidden from List (toggle with `h`).
+    hide_done: bool,
+    /// Keep pinned Done tasks visible while Done tasks are hidden.
+    show_pinned_done: bool,
+    /// Zero-pad IDs to this many digits in tables and pickers (0 = no padding).
+    id_display_width: usize,
+    /// Table columns after ID, in order: title, description, status, points, url.
+    columns: Vec<String>,
+    /// How alike two titles must be (0.0-1.0) to be flagged as near-duplicates.
+    similar_title_threshold: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            json_indent: "  ".into(),
+            set_terminal_title: true,
+            start_id: None,
+            normalize_titles: false,
+            title_case: false,
+            data_file: TASKS_FILE.into(),
+            default_status: TaskStatus::Todo,
+            ascii_symbols: false,
+            menu: ["add", "list", "remove", "save", "update", "followup", "pin", "split", "waiting", "risky", "duplicates", "copy", "import", "report", "todoist", "reload", "open", "exit"]
+                .map(String::from)
+                .to_vec(),
+            empty_message: None,
+            quick_capture: false,
+            empty_description_placeholder: None,
+            max_tasks: None,
+            block_at_max_tasks: false,
+            confirm_complete: false,
+            flash_on_action: false,
+            hide_done: false,
+            show_pinned_done: true,
+            id_display_width: 0,
+            columns: ["title", "description", "status", "points", "url"].map(String::from).to_vec(),
+            similar_title_threshold: 0.85,
+        }
+    }
+}
+
+fn load_config() -> Config {
+    let mut config = match std::fs::read_to_string(CONFIG_FILE) {
         Ok(s) if !s.trim().is_empty() => {
-            match serde_json::from_str::<Vec<Task>>(&s) {
-                Ok(v) => v,
+            match serde_json::from_str::<Config>(&s) {
+                Ok(c) => c,
                 Err(e) => {
-                    eprintln!("Could not parse {TASKS_FILE}: {e}. Starting empty.");
-                    Vec::new()
+                    eprintln!("Could not parse {CONFIG_FILE}: {e}. Using defaults.");
+                    Config::default()
                 }
             }
         }
-        Ok(_) => Vec::new(), // empty file
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Ok(_) => Config::default(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
         Err(e) => {
-            eprintln!("Could not read {TASKS_FILE}: {e}. Starting empty.");
-            Vec::new()
+            eprintln!("Could not read {CONFIG_FILE}: {e}. Using defaults.");
+            Config::default()
+        }
+    };
+
+    if !config.json_indent.chars().all(|c| c == ' ' || c == '\t') {
+        eprintln!("Ignoring json_indent {:?}: only spaces and tabs are allowed.", config.json_indent);
+        config.json_indent = Config::default().json_indent;
+    }
+    if config.start_id == Some(0) {
+        eprintln!("Ignoring start_id 0: IDs start at 1.");
+        config.start_id = None;
+    }
+    config.menu.retain(|name| {
+        let known = MenuChoice::from_name(name).is_some();
+        if !known {
+            eprintln!("Ignoring unknown menu action {name:?}.");
         }
+        known
+    });
+    if !(0.0..=1.0).contains(&config.similar_title_threshold) {
+        eprintln!("Ignoring similar_title_threshold {}: must be between 0 and 1.", config.similar_title_threshold);
+        config.similar_title_threshold = Config::default().similar_title_threshold;
     }
+    config.columns.retain(|name| {
+        // ID is always shown; accept it in the list without complaint
+        let known = name.trim().eq_ignore_ascii_case("id") || Column::from_name(name).is_some();
+        if !known {
+            eprintln!("Ignoring unknown column {name:?}.");
+        }
+        known
+    });
+    if config.menu.is_empty() {
+        eprintln!("The menu list is empty; showing every action.");
+        config.menu = Config::default().menu;
+    }
+    config
 }
 
-fn save_tasks(tasks: &[Task]) {
-    // Write atomically: to a temp file, then rename
-    let tmp = format!("{TASKS_FILE}.tmp");
-    match serde_json::to_string_pretty(tasks) {
-        Ok(json) => {
-            if let Err(e) = std::fs::write(&tmp, json) {
-                eprintln!("Failed to write temp file: {e}");
-                return;
+// Like `serde_json::to_string_pretty`, but with a configurable indent
+fn to_json_pretty<T: Serialize + ?Sized>(value: &T, indent: &str) -> serde_json::Result<String> {
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    value.serialize(&mut ser)?;
+    Ok(String::from_utf8(buf).expect("serde_json always writes valid UTF-8"))
+}
+
+// Things the app remembers about itself between runs (not user settings)
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct AppState {
+    onboarded: bool,
+}
+
+fn load_state(path: &std::path::Path) -> AppState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &std::path::Path, state: &AppState) -> Result<(), AppError> {
+    std::fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+// Task files are `{ "version": N, "tasks": [...] }`. Files from before
+// versioning are a bare array and count as version 1.
+const DATA_VERSION: u64 = 2;
+
+#[derive(Serialize)]
+struct TaskFile<'a> {
+    version: u64,
+    tasks: &'a [Task],
+}
+
+// Brings any supported file layout to the current one and returns the raw
+// task objects. 1 -> 2 only added the wrapper; later migrations go here.
+fn task_values(doc: serde_json::Value) -> Result<Vec<serde_json::Value>, String> {
+    match doc {
+        serde_json::Value::Array(tasks) => Ok(tasks),
+        serde_json::Value::Object(mut obj) => {
+            let version = obj.get("version").and_then(|v| v.as_u64()).ok_or("missing \"version\"")?;
+            if version > DATA_VERSION {
+                return Err(format!("file version {version} is newer than this app supports ({DATA_VERSION})"));
             }
-            if let Err(e) = std::fs::rename(&tmp, TASKS_FILE) {
-                eprintln!("Failed to replace {TASKS_FILE}: {e}");
+            match obj.remove("tasks") {
+                Some(serde_json::Value::Array(tasks)) => Ok(tasks),
+                _ => Err("missing \"tasks\" list".into()),
             }
         }
-        Err(e) => eprintln!("Failed to serialize tasks: {e}"),
+        _ => Err("expected a task list or a versioned task file".into()),
     }
 }
 
+fn parse_tasks(s: &str) -> Result<Vec<Task>, AppError> {
+    let doc: serde_json::Value = serde_json::from_str(s)?;
+    let values = task_values(doc).map_err(|msg| AppError::Json(serde::de::Error::custom(msg)))?;
+    Ok(serde_json::from_value(serde_json::Value::Array(values))?)
+}
 
-// ===================
-// Program entry point
-// ===================
+fn load_versioned(path: &str) -> Result<Vec<Task>, AppError> {
+    log::debug!("Loading tasks from {path}");
+    match std::fs::read_to_string(path) {
+        Ok(s) if !s.trim().is_empty() => parse_tasks(&s),
+        Ok(_) => Ok(Vec::new()), // empty file
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
 
-fn main() -> io::Result<()> {
-#[cfg(windows)]
-    {
-        if maybe_relaunch_in_terminal() {
-            // Exit the original process cleanly (type is io::Result<()>)
-            return Ok(());
+fn save_tasks(tasks: &[Task], config: &Config) -> Result<(), AppError> {
+    // Write atomically: to a temp file, then rename
+    let tmp = format!("{}.tmp", config.data_file);
+    log::debug!("Saving {} task(s) to {} via {tmp}", tasks.len(), config.data_file);
+    let json = to_json_pretty(&TaskFile { version: DATA_VERSION, tasks }, &config.json_indent)?;
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, &config.data_file)?;
+    Ok(())
+}
+
+// What the task file looked like the last time we read or wrote it
+struct DiskSnapshot {
+    modified: Option<SystemTime>,
+    // Each task as JSON, by ID: the common ancestor for `merge_external`
+    tasks: HashMap<u32, serde_json::Value>,
+}
+
+impl DiskSnapshot {
+    fn capture(path: &str, tasks: &[Task]) -> DiskSnapshot {
+        DiskSnapshot {
+            modified: std::fs::metadata(path).and_then(|m| m.modified()).ok(),
+            tasks: tasks.iter().filter_map(|t| Some((t.id, serde_json::to_value(t).ok()?))).collect(),
         }
     }
 
-    #[cfg(windows)]
-    disable_resize();
+    fn changed_on_disk(&self, path: &str) -> bool {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok() != self.modified
+    }
+}
 
-    let mut tasks: Vec<Task> = load_tasks();
-    let mut next_id: u32 = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+// What `merge_external` took from the file
+#[derive(Debug, Default, PartialEq, Eq)]
+struct MergeSummary {
+    added: usize,
+    updated: usize,
+    removed: usize,
+}
 
+// Folds edits made to the file outside the app into `tasks`, task by task,
+// against `base` (the file as we last read or wrote it). Whichever side
+// changed a task wins, deletions included; tasks new on disk are added
+// (renumbered if one added here took the ID). When both sides changed the
+// same task, `pick_disk(mine, theirs)` decides; `None` is a deleted copy.
+fn merge_external(
+    tasks: &mut Vec<Task>,
+    on_disk: Vec<Task>,
+    base: &HashMap<u32, serde_json::Value>,
+    mut pick_disk: impl FnMut(Option<&Task>, Option<&Task>) -> bool,
+) -> MergeSummary {
+    let unchanged = |t: Option<&Task>, id: u32| {
+        t.and_then(|t| serde_json::to_value(t).ok()).as_ref() == base.get(&id)
+    };
+    let mut summary = MergeSummary::default();
+    let mut theirs = on_disk;
+    let mut merged = Vec::with_capacity(tasks.len());
+    // On disk with no common ancestor: added there since we last synced
+    let mut new_on_disk = Vec::new();
+
+    for mine in tasks.drain(..) {
+        let disk_copy = theirs.iter().position(|t| t.id == mine.id).map(|i| theirs.remove(i));
+        if !base.contains_key(&mine.id) {
+            // Added here; a disk task with the same ID was added there
+            merged.push(mine);
+            new_on_disk.extend(disk_copy);
+            continue;
+        }
+        let id = mine.id;
+        let take_disk = if unchanged(Some(&mine), id) {
+            !unchanged(disk_copy.as_ref(), id)
+        } else if unchanged(disk_copy.as_ref(), id) {
+            false
+        } else {
+            let same = disk_copy.as_ref().and_then(|t| serde_json::to_value(t).ok()) == serde_json::to_value(&mine).ok();
+            !same && pick_disk(Some(&mine), disk_copy.as_ref())
+        };
+        match (take_disk, disk_copy) {
+            (true, Some(t)) => {
+                merged.push(t);
+                summary.updated += 1;
+            }
+            (true, None) => summary.removed += 1,
+            (false, _) => merged.push(mine),
+        }
+    }
 
-    loop {
-        // Show the TUI menu; returns a choice or None (q)
-        let Some(choice) = run_menu_tui()? else { break };
+    for t in theirs {
+        if !base.contains_key(&t.id) {
+            new_on_disk.push(t);
+        } else if !unchanged(Some(&t), t.id) && pick_disk(None, Some(&t)) {
+            // Deleted here but edited there
+            merged.push(t);
+            summary.added += 1;
+        }
+    }
 
-        match choice {
-            MenuChoice::Add => {
-                if let Some(task) = prompt_add_task(next_id) {
-                    add_task(&mut tasks, task);
-                    next_id += 1;
-                    save_tasks(&tasks);
-                }
-                wait_enter();
-            }
+    let mut next_id = merged.iter().chain(&new_on_disk).map(|t| t.id).max().unwrap_or(0) + 1;
+    for mut t in new_on_disk {
+        if merged.iter().any(|o| o.id == t.id) {
+            t.id = next_id;
+            next_id += 1;
+        }
+        merged.push(t);
+        summary.added += 1;
+    }
 
-             MenuChoice::List => {
-                if tasks.is_empty() {
-                    println!("No tasks yet.");
-                } else {
-                    list_tasks(&tasks);
-                }
-                wait_enter();
+    *tasks = merged;
+    summary
+}
+
+// Asks which copy of a task both the app and `path` changed to keep
+fn prompt_merge_conflict(path: &str, mine: Option<&Task>, theirs: Option<&Task>, config: &Config) -> bool {
+    let Some(id) = mine.or(theirs).map(|t| t.id) else { return false };
+    say!("\n{} task #{id} was changed both here and in {path}.", "Conflict:".yellow().bold());
+    for (label, copy) in [("Mine", mine), ("In the file", theirs)] {
+        match copy {
+            Some(t) => {
+                say!("{label}:");
+                list_tasks([t], config);
             }
+            None => say!("{label}: (deleted)"),
+        }
+    }
+    let theme = ColorfulTheme::default();
+    let options = [format!("Keep mine for #{id}"), format!("Take the file's #{id}")];
+    prompt_select(&theme, "Which one?", &options, 0) == Some(1)
+}
 
-            MenuChoice::Remove => {
-                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to remove") {
-                    let theme = ColorfulTheme::default();
-                    if prompt_confirm(&theme, &format!("Delete task #{}?", id)) {
-                        remove_task(&mut tasks, id);
-                        save_tasks(&tasks);
-                    } else {
-                        println!("Cancelled.");
-                    }
+// Saves after a change; failures are reported but don't end the session.
+// If the task file was edited elsewhere since we last read or wrote it, asks
+// whether to merge those edits in or overwrite them first.
+// Returns whether the save succeeded.
+fn save_and_report(tasks: &mut Vec<Task>, config: &Config, disk: &mut DiskSnapshot) -> bool {
+    let path = config.data_file.as_str();
+    if disk.changed_on_disk(path) {
+        log::debug!("{path} modified since {:?}", disk.modified);
+        let theme = ColorfulTheme::default();
+        let options = ["Reload and merge", "Overwrite with my version"];
+        match prompt_select(&theme, &format!("{path} was changed outside the app"), &options, 0) {
+            Some(0) => match load_versioned(path) {
+                Ok(on_disk) => {
+                    let summary = merge_external(tasks, on_disk, &disk.tasks, |mine, theirs| {
+                        prompt_merge_conflict(path, mine, theirs, config)
+                    });
+                    say!(
+                        "Merged from {path}: {} added, {} updated, {} removed.",
+                        summary.added, summary.updated, summary.removed
+                    );
                 }
-                wait_enter();
+                Err(e) => {
+                    say_err!("Could not reload {path}: {e}. Not saved.");
+                    return false;
+                }
+            },
+            Some(_) => {}
+            None => {
+                say!("Not saved.");
+                return false;
             }
+        }
+    }
 
-            MenuChoice::Save => {
-                let json = serde_json::to_string_pretty(&tasks).unwrap();
-                match std::fs::write("tasks.json", json) {
-                    Ok(_) => {
-                        save_tasks(&tasks);
-                        println!("Saved to {TASKS_FILE}");
-                    },
-                    Err(e) => println!("Failed to save: {e}"),
+    match save_tasks(tasks, config) {
+        Ok(()) => {
+            *disk = DiskSnapshot::capture(path, tasks);
+            *PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            true
+        }
+        Err(e) => {
+            log::debug!("Save failed: {e:?}");
+            say_err!("Failed to save {path}: {e}");
+            if let Ok(json) = to_json_pretty(&TaskFile { version: DATA_VERSION, tasks }, &config.json_indent) {
+                *PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()) = Some((path.to_string(), json));
+            }
+            false
+        }
+    }
+}
+
+// (path, JSON) of the last save that failed, so the Ctrl+C handler can
+// retry it without reaching into main's state
+static PENDING_SAVE: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+// Ctrl+C with the plain menu arrives as SIGINT. The TUI runs in raw mode,
+// where Ctrl+C is an ordinary key: the menu treats it like q, and its
+// dialogs call `exit_on_interrupt` themselves, so this never fires in the
+// middle of a draw.
+//
+// Every change is saved as soon as it's made, so all the handler has to
+// rescue is a save that failed (PENDING_SAVE). What it can't rescue:
+// - input typed into a prompt that hasn't been answered yet, since the
+//   change it describes was never made
+// - a change waiting on the "changed outside the app" question in
+//   `save_and_report`; writing it then would overwrite the other edits
+//   without asking, so it never goes into PENDING_SAVE
+fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| exit_on_interrupt());
+    if let Err(e) = result {
+        eprintln!("Could not install the Ctrl+C handler: {e}");
+    }
+}
+
+
+fn exit_on_interrupt() -> ! {
+    // Undo whatever a prompt or the TUI left behind
+    if io::stdout().is_terminal() {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, crossterm::cursor::Show);
+    }
+    restore_terminal_title();
+    println!();
+
+    let pending = PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some((path, json)) = pending {
+        match std::fs::write(&path, json) {
+            Ok(()) => println!("Saved unsaved changes to {path}."),
+            Err(e) => eprintln!("Could not save {path}: {e}"),
+        }
+    }
+    std::process::exit(130);
+}
+
+// ======================
+// Command-line arguments
+// ======================
+
+const USAGE: &str = "\
+Usage: main [OPTIONS]
+
+Options:
+  --file <PATH>           Task file to use (default: tasks.json)
+  --default-status <S>    Status preselected when adding: todo, inprogress, blocked, done
+  --start-id <N>          First task ID for an empty list (N > 0)
+  --read-only             Browse tasks without allowing any changes
+  --no-tui                Use a plain numbered menu instead of the full-screen one
+  --no-log                Don't append actions to todo.log
+  --doctor                Check the task file for problems and exit (non-zero if any)
+  -v, --verbose           Print diagnostics (relaunch, load/save) to stderr
+  --tsv                   Print tasks as tab-separated id, title, status and exit
+  --status <S>            With --tsv, only tasks with this status
+  -h, --help              Print this help
+
+Environment:
+  TODO_FILE               Same as --file
+  TODO_DEFAULT_STATUS     Same as --default-status
+
+Settings are applied in order: config.json, then environment, then flags.";
+
+#[derive(Debug, Default)]
+struct Args {
+    file: Option<String>,
+    default_status: Option<TaskStatus>,
+    start_id: Option<u32>,
+    read_only: bool,
+    no_tui: bool,
+    no_log: bool,
+    doctor: bool,
+    verbose: bool,
+    tsv: bool,
+    status: Option<TaskStatus>,
+    help: bool,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--file" => parsed.file = Some(it.next().ok_or("--file needs a path")?),
+            "--default-status" => {
+                let value = it.next().ok_or("--default-status needs a value")?;
+                match parse_status(&value) {
+                    Some(status) => parsed.default_status = Some(status),
+                    None => return Err(format!("Unknown status {value:?}")),
+                }
+            }
+            "--start-id" => {
+                let value = it.next().ok_or("--start-id needs a value")?;
+                match value.parse::<u32>() {
+                    Ok(n) if n > 0 => parsed.start_id = Some(n),
+                    _ => return Err(format!("--start-id must be a positive integer, got {value:?}")),
+                }
+            }
+            "--read-only" => parsed.read_only = true,
+            "--no-tui" => parsed.no_tui = true,
+            "--no-log" => parsed.no_log = true,
+            "--doctor" => parsed.doctor = true,
+            "-v" | "--verbose" => parsed.verbose = true,
+            "--tsv" => parsed.tsv = true,
+            "--status" => {
+                let value = it.next().ok_or("--status needs a value")?;
+                match parse_status(&value) {
+                    Some(status) => parsed.status = Some(status),
+                    None => return Err(format!("Unknown status {value:?}")),
+                }
+            }
+            "-h" | "--help" => parsed.help = true,
+            other => return Err(format!("Unknown argument: {other}")),
+        }
+    }
+    if parsed.status.is_some() && !parsed.tsv {
+        return Err("--status only works together with --tsv".into());
+    }
+    Ok(parsed)
+}
+
+// Layers environment variables and then flags over config.json
+fn apply_overrides(config: &mut Config, args: &Args) {
+    if let Ok(file) = std::env::var("TODO_FILE")
+        && !file.trim().is_empty()
+    {
+        config.data_file = file;
+    }
+    if let Ok(value) = std::env::var("TODO_DEFAULT_STATUS") {
+        match parse_status(&value) {
+            Some(status) => config.default_status = status,
+            None => eprintln!("Ignoring TODO_DEFAULT_STATUS={value:?}: unknown status."),
+        }
+    }
+
+    if let Some(file) = &args.file {
+        config.data_file = file.clone();
+    }
+    if let Some(status) = &args.default_status {
+        config.default_status = status.clone();
+    }
+}
+
+// Backslash-escapes what would break a TSV line
+fn tsv_field(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+// `--tsv`: one `id<TAB>title<TAB>status` line per task, no colors or borders
+fn print_tsv(tasks: &[Task], status: Option<&TaskStatus>) {
+    for t in tasks.iter().filter(|t| status.is_none_or(|s| t.status == *s)) {
+        println!("{}\t{}\t{:?}", t.id, tsv_field(&t.title), t.status);
+    }
+}
+
+// `--doctor`: prints a pass/fail checklist for the task file. Returns
+// whether every check passed.
+fn run_doctor(path: &str, config: &Config) -> bool {
+    let mut ok = true;
+    let mut check = |passed: bool, what: String| {
+        if passed {
+            println!("{} {what}", "✔".green());
+        } else {
+            println!("{} {what}", "✘".red());
+            ok = false;
+        }
+    };
+
+    let raw = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            check(false, format!("{path} is readable ({e})"));
+            return false;
+        }
+    };
+    check(true, format!("{path} is readable"));
+
+    // Look at statuses as plain strings first so a typo is reported by task
+    // instead of failing the whole parse
+    let values = match serde_json::from_str(&raw).map_err(|e| e.to_string()).and_then(task_values) {
+        Ok(v) => v,
+        Err(e) => {
+            check(false, format!("{path} is a task file ({e})"));
+            return false;
+        }
+    };
+    check(true, format!("{path} is a task file"));
+
+    // Name each offending task by its ID, as written in the file
+    let label = |v: &serde_json::Value| match v.get("id") {
+        Some(id) => format!("#{id}"),
+        None => "(no id)".to_string(),
+    };
+
+    let statuses = ["Todo", "InProgress", "Blocked", "Done"];
+    let unknown: Vec<String> = values.iter()
+        .filter(|v| v.get("status").and_then(|s| s.as_str()).is_none_or(|s| !statuses.contains(&s)))
+        .map(label)
+        .collect();
+    check(unknown.is_empty(), if unknown.is_empty() {
+        "All statuses are known".into()
+    } else {
+        format!("Unknown or missing status: {}", unknown.join(", "))
+    });
+
+    let mut seen = HashSet::new();
+    let mut dups: Vec<u64> = values.iter()
+        .filter_map(|v| v.get("id").and_then(|id| id.as_u64()))
+        .filter(|id| !seen.insert(*id))
+        .collect();
+    dups.sort_unstable();
+    dups.dedup();
+    let ids: Vec<String> = dups.iter().map(|id| format!("#{id}")).collect();
+    check(dups.is_empty(), if dups.is_empty() {
+        "IDs are unique".into()
+    } else {
+        format!("Duplicate IDs: {}", ids.join(", "))
+    });
+
+    let untitled: Vec<String> = values.iter()
+        .filter(|v| v.get("title").and_then(|t| t.as_str()).is_none_or(|t| t.trim().is_empty()))
+        .map(label)
+        .collect();
+    check(untitled.is_empty(), if untitled.is_empty() {
+        "Every task has a title".into()
+    } else {
+        format!("Empty titles: {}", untitled.join(", "))
+    });
+
+    match serde_json::from_value::<Vec<Task>>(serde_json::Value::Array(values)) {
+        Ok(tasks) => {
+            check(true, "All tasks load".into());
+            // Worth a look, but not an error: same text on purpose is legit
+            let groups = duplicate_descriptions(&tasks, config);
+            if !groups.is_empty() {
+                let listed: Vec<String> = groups.iter()
+                    .map(|g| g.iter().map(|t| format!("#{}", t.id)).collect::<Vec<_>>().join(", "))
+                    .collect();
+                println!("{} Tasks sharing a description: {}", "!".yellow(), listed.join("; "));
+            }
+            let pairs = similar_titles(&tasks, config.similar_title_threshold);
+            if !pairs.is_empty() {
+                let listed: Vec<String> = pairs.iter().map(|(a, b, _)| format!("#{} ~ #{}", a.id, b.id)).collect();
+                println!("{} Near-duplicate titles: {}", "!".yellow(), listed.join(", "));
+            }
+        }
+        Err(e) => check(false, format!("All tasks load ({e})")),
+    }
+
+    // Opening for append checks permissions without touching the contents
+    match std::fs::OpenOptions::new().append(true).open(path) {
+        Ok(_) => check(true, format!("{path} is writable")),
+        Err(e) => check(false, format!("{path} is writable ({e})")),
+    }
+
+    ok
+}
+
+// ===================
+// Program entry point
+// ===================
+
+// Returning the error from main would print its Debug form; by the time it
+// gets here the terminal guard has restored the screen for the message
+fn main() {
+    let result = run();
+    restore_terminal_title();
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), AppError> {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{msg}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    // Diagnostics stay off unless asked for
+    env_logger::Builder::new()
+        .filter_level(if args.verbose { log::LevelFilter::Debug } else { log::LevelFilter::Off })
+        .init();
+
+#[cfg(windows)]
+    {
+        // --doctor and --tsv are meant for scripts/CI; keep them in the calling console
+        if !args.doctor && !args.tsv && maybe_relaunch_in_terminal() {
+            // Exit the original process cleanly
+            return Ok(());
+        }
+    }
+
+    #[cfg(windows)]
+    disable_resize();
+
+    install_interrupt_handler();
+
+    let mut config = load_config();
+    apply_overrides(&mut config, &args);
+    let config = config;
+    log::debug!("{config:?}");
+
+    if args.doctor {
+        let healthy = run_doctor(&config.data_file, &config);
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
+    if args.tsv {
+        match load_versioned(&config.data_file) {
+            Ok(tasks) => print_tsv(&tasks, args.status.as_ref()),
+            Err(e) => {
+                eprintln!("Could not load {}: {e}", config.data_file);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // Starting empty instead would let the first change overwrite the file,
+    // which for a newer version's file is the one thing we must not do
+    let mut tasks: Vec<Task> = match load_versioned(&config.data_file) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            eprintln!("Could not load {}: {e}", config.data_file);
+            eprintln!("The file was left untouched. Fix it (--doctor lists problems) or use --file for another list.");
+            std::process::exit(1);
+        }
+    };
+
+    let mut disk = DiskSnapshot::capture(&config.data_file, &tasks);
+
+    // Raw mode and the alternate screen need a real terminal
+    let use_tui = !args.no_tui && io::stdout().is_terminal() && io::stdin().is_terminal();
+    // With the plain menu: dialoguer draws on stderr and reads keys from the
+    // terminal, so a pipe on either end gets plain line prompts
+    PLAIN_PROMPTS.store(!io::stdin().is_terminal() || !io::stderr().is_terminal(), Ordering::Relaxed);
+    // The TUI keeps the alternate screen for the whole session; prompts and
+    // messages are drawn over the menu (see `Overlay`)
+    let mut screen = OverlayScreen;
+    let tui_guard = if use_tui {
+        let terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        *OVERLAY.lock().unwrap_or_else(|e| e.into_inner()) = Some(Overlay { terminal, backdrop: Buffer::default(), lines: Vec::new() });
+        Some(TerminalGuard::enter(&mut screen)?)
+    } else {
+        None
+    };
+    let menu = menu_items(&config.menu);
+    // Toggled with `h` in the menu; starts from config
+    let mut hide_done = config.hide_done;
+    // Set after a successful change so the next menu draw flashes once
+    let mut flash = false;
+    // Opened on first use and kept: on X11 the copied text only stays
+    // available while the clipboard handle is alive
+    let mut clipboard: Option<arboard::Clipboard> = None;
+
+    // Every change is saved right away; this only stays set when that save failed
+    let mut dirty = false;
+
+    let log_file = beside_data_file(&config, LOG_FILE);
+    let log = |msg: String| {
+        if !args.no_log {
+            log_action(&log_file, &msg);
+        }
+    };
+
+    let dups = duplicate_ids(&tasks);
+    if !dups.is_empty() {
+        let ids: Vec<String> = dups.iter().map(|id| format!("#{id}")).collect();
+        say!("{} {}", "Duplicate task IDs found:".yellow(), ids.join(", "));
+        let theme = ColorfulTheme::default();
+        if !args.read_only && prompt_confirm(&theme, "Reassign the duplicates to new IDs?", true) {
+            for (old, new) in dedupe_ids(&mut tasks) {
+                say!("Task #{old} is now #{new}");
+                log(format!("renumbered duplicate #{old} to #{new}"));
+            }
+            dirty = !save_and_report(&mut tasks, &config, &mut disk);
+        }
+    }
+
+    // --start-id / start_id only matter for a fresh list; never reuse loaded IDs
+    let mut next_id: u32 = match tasks.iter().map(|t| t.id).max() {
+        Some(max) => max + 1,
+        None => args.start_id.or(config.start_id).unwrap_or(1),
+    };
+
+    // First run: no data file yet and nothing loaded
+    let first_run = tasks.is_empty() && !std::path::Path::new(&config.data_file).exists();
+    if first_run && !args.read_only && use_tui {
+        let state_file = beside_data_file(&config, STATE_FILE);
+        let mut state = load_state(&state_file);
+        if !state.onboarded {
+            if with_overlay(|o| run_onboarding_tui(&mut o.terminal, &config.data_file)).unwrap_or(Ok(false))? {
+                let sample = Task::new(
+                    next_id,
+                    "Try out the To-Do app".into(),
+                    "Mark me Done with 5, or remove me with 3.".into(),
+                    TaskStatus::Todo,
+                );
+                log(format!("added #{} {:?} (sample)", sample.id, sample.title));
+                add_task(&mut tasks, sample);
+                dirty = !save_and_report(&mut tasks, &config, &mut disk);
+            }
+            state.onboarded = true;
+            if let Err(e) = save_state(&state_file, &state) {
+                say_err!("Could not save {}: {e}", state_file.display());
+            }
+        }
+    }
+
+    let mut startup_banner = risk_banner(&tasks);
+
+    loop {
+        // Imports and merges can bring in IDs past the counter
+        next_id = next_id.max(tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1);
+
+        if config.set_terminal_title && io::stdout().is_terminal() {
+            update_terminal_title(&tasks);
+        }
+
+        // Only on the first visit to the menu
+        let banner = startup_banner.take();
+        let picked = match with_overlay(|o| run_menu_tui(o, &menu, args.read_only, &mut hide_done, flash, banner.as_deref())) {
+            Some(picked) => picked?,
+            None => {
+                if let Some(line) = &banner {
+                    println!("\n{}", line.red().bold());
+                }
+                run_menu_plain(&menu, args.read_only, &mut hide_done)?
+            }
+        };
+        let choice = match picked {
+            MenuExit::Chose(choice) => choice,
+            // 'q' quits straight away unless there are unsaved changes,
+            // in which case it goes through the same confirm-and-save as Exit
+            MenuExit::Quit if !dirty => break,
+            MenuExit::Quit => MenuChoice::Exit,
+            // No one left to confirm anything: retry a failed save (which
+            // reports if it fails again) and stop
+            MenuExit::InputClosed => {
+                if dirty {
+                    save_and_report(&mut tasks, &config, &mut disk);
+                }
+                break;
+            }
+        };
+
+        // A mutating action succeeded if it got the file written
+        let saved_before = disk.modified;
+        match choice {
+            MenuChoice::Add => {
+                // Keep adding until "Add another?" is declined
+                let theme = ColorfulTheme::default();
+                let mut added = 0;
+                while allow_new_task(tasks.len(), &config)
+                    && let Some(mut task) = prompt_add_task(next_id, &config.default_status)
+                {
+                    task.title = tidy_title(task.title, &config);
+                    task.description = tidy_description(task.description, &config);
+                    log(format!("added #{} {:?}", task.id, task.title));
+                    add_task(&mut tasks, task);
+                    next_id += 1;
+                    added += 1;
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+
+                    if !prompt_confirm(&theme, "Add another?", config.quick_capture) {
+                        break;
+                    }
+                }
+                if added > 1 {
+                    say!("Added {added} tasks.");
+                }
+                // "Add another?" already paused after a single add
+                if added != 1 {
+                    wait_enter();
+                }
+            }
+
+             MenuChoice::List => {
+                let shown: Vec<&Task> = tasks.iter()
+                    .filter(|t| !hide_done || t.status != TaskStatus::Done || (t.pinned && config.show_pinned_done))
+                    .collect();
+                let hidden = tasks.len() - shown.len();
+                if tasks.is_empty() {
+                    say!("{}", empty_list_message(&config));
+                } else if shown.is_empty() {
+                    say!("All {hidden} task(s) are Done and hidden.");
+                } else {
+                    // Only worth asking when there's more than one group to show
+                    let theme = ColorfulTheme::default();
+                    let mixed = shown.iter().any(|t| t.status != shown[0].status);
+                    match if mixed { prompt_group_key(&theme) } else { None } {
+                        Some(key) => list_tasks_grouped(shown, key, &config),
+                        None => list_tasks(shown, &config),
+                    }
+                }
+                if hidden > 0 {
+                    say!("{}", format!("{hidden} done task(s) hidden; press h in the menu to show them.").dimmed());
                 }
                 wait_enter();
             }
 
-            MenuChoice::Update => {
-                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to update") {
+            MenuChoice::Remove => {
+                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to remove", &config) {
                     let theme = ColorfulTheme::default();
-                    if let Some(new_status) = prompt_status(&theme, "New status") {
-                        let mut found = false;
-                        for t in &mut tasks {
-                            if t.id == id {
-                                t.status = new_status.clone();
-                                found = true;
-                                println!("Task #{} updated.", id);
-                                save_tasks(&tasks);
-                                break;
+                    if prompt_confirm(&theme, &format!("Delete task #{}?", id), false) {
+                        remove_task(&mut tasks, id);
+                        log(format!("removed #{id}"));
+                        dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                    } else {
+                        say!("Cancelled.");
+                    }
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Save => {
+                dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                if !dirty {
+                    say!("Saved to {}", config.data_file);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Update => {
+                let theme = ColorfulTheme::default();
+                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to update", &config)
+                    && let Some(idx) = tasks.iter().position(|t| t.id == id)
+                    && let Some(new_status) = prompt_status(&theme, "New status", &tasks[idx].status)
+                    && (new_status != TaskStatus::Done || confirm_complete(id, &config))
+                {
+                    tasks[idx].status = new_status.clone();
+                    say!("Task #{} updated.", id);
+                    log(format!("set #{id} status to {new_status:?}"));
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Pin => {
+                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to pin/unpin", &config)
+                    && let Some(t) = tasks.iter_mut().find(|t| t.id == id)
+                {
+                    t.pinned = !t.pinned;
+                    let what = if t.pinned { "pinned" } else { "unpinned" };
+                    say!("Task #{id} {what}.");
+                    log(format!("{what} #{id}"));
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Waiting => {
+                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to set/clear waiting on", &config)
+                    && let Some(t) = tasks.iter_mut().find(|t| t.id == id)
+                    && let Some(waiting_on) = prompt_waiting_on(t)
+                {
+                    match &waiting_on {
+                        Some(who) => {
+                            say!("Task #{id} is waiting on {who}.");
+                            log(format!("#{id} waiting on {who:?}"));
+                        }
+                        None => {
+                            say!("Task #{id} is no longer waiting.");
+                            log(format!("cleared waiting on #{id}"));
+                        }
+                    }
+                    t.waiting_on = waiting_on;
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::FollowUp => {
+                if allow_new_task(tasks.len(), &config)
+                    && let Some(id) = prompt_select_task_id(&tasks, "Pick the task you finished", &config)
+                    && let Some(idx) = tasks.iter().position(|t| t.id == id)
+                    && confirm_complete(id, &config)
+                    && let Some((title, description)) = prompt_follow_up(&tasks[idx])
+                {
+                    let done = &mut tasks[idx];
+                    done.status = TaskStatus::Done;
+                    let task = done.spin_off(next_id, tidy_title(title, &config), tidy_description(description, &config), config.default_status.clone());
+                    log(format!("set #{id} status to Done"));
+                    log(format!("added #{} {:?} (follow-up to #{id})", task.id, task.title));
+                    tasks.push(task);
+                    say!("Task #{id} done; follow-up is #{next_id}.");
+                    next_id += 1;
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Split => {
+                if allow_new_task(tasks.len(), &config)
+                    && let Some(id) = prompt_select_task_id(&tasks, "Pick a task to split", &config)
+                    && let Some(idx) = tasks.iter().position(|t| t.id == id)
+                    && let Some((title, kept)) = prompt_split_task(&tasks[idx])
+                {
+                    let original = &mut tasks[idx];
+                    original.description = tidy_description(kept, &config);
+                    let title = tidy_title(title, &config);
+                    let task = original.spin_off(next_id, title, tidy_description(String::new(), &config), original.status.clone());
+                    tasks.push(task);
+                    say!("Split #{id} into #{id} and #{next_id}.");
+                    log(format!("split #{id} into #{id} and #{next_id}"));
+                    next_id += 1;
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Copy => {
+                if let Some(id) = prompt_select_task_id(&tasks, "Pick a task to copy", &config)
+                    && let Some(t) = tasks.iter().find(|t| t.id == id)
+                {
+                    let json = serde_json::to_string_pretty(t)?;
+                    if clipboard.is_none() {
+                        clipboard = arboard::Clipboard::new().ok();
+                    }
+                    match clipboard.as_mut().map(|c| c.set_text(json.clone())) {
+                        Some(Ok(())) => say!("Copied task #{id} to clipboard."),
+                        _ => say!("No clipboard available; here is task #{id}:\n{json}"),
+                    }
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Import => {
+                let theme = ColorfulTheme::default();
+                // Empty means the current folder
+                let dir = prompt_text(&theme, "Folder or .txt file to import from [.]", "", |_| Ok(()))
+                    .map(|d| if d.trim().is_empty() { ".".to_string() } else { d });
+                if let Some(dir) = dir {
+                    let is_text = std::path::Path::new(dir.trim())
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"));
+                    let result = if is_text {
+                        import_text(next_id, dir.trim())
+                    } else {
+                        let own_files = [
+                            std::path::PathBuf::from(&config.data_file),
+                            std::path::PathBuf::from(CONFIG_FILE),
+                            beside_data_file(&config, STATE_FILE),
+                            beside_data_file(&config, TODOIST_FILE),
+                        ];
+                        import_dir(next_id, dir.trim(), &own_files)
+                    };
+                    match result {
+                        Ok(batch) if batch.is_empty() => say!("No tasks imported."),
+                        Ok(mut batch) => {
+                            let found = batch.len();
+                            batch.truncate(allow_new_tasks(tasks.len(), found, &config));
+                            let n = batch.len();
+                            tasks.extend(batch);
+                            match n {
+                                0 => say!("No tasks imported."),
+                                n if n < found => say!("Imported {n} of {found} task(s) from {}.", dir.trim()),
+                                n => say!("Imported {n} task(s) from {}.", dir.trim()),
+                            }
+                            if n > 0 {
+                                log(format!("imported {n} task(s) from {}", dir.trim()));
+                                dirty = !save_and_report(&mut tasks, &config, &mut disk);
                             }
                         }
-                        if !found {
-                            println!("Task not found.");
+                        Err(e) => say!("Failed to read {}: {e}", dir.trim()),
+                    }
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Risky => {
+                // Riskiest first; the sort is stable so ties keep list order
+                let mut risky: Vec<&Task> = tasks.iter()
+                    .filter(|t| t.status != TaskStatus::Done)
+                    .filter(|t| matches!(t.risk, Some(RiskLevel::High | RiskLevel::Medium)))
+                    .collect();
+                risky.sort_by_key(|t| t.risk != Some(RiskLevel::High));
+                if risky.is_empty() {
+                    say!("No open tasks are flagged as risky.");
+                } else {
+                    list_tasks(risky, &config);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Duplicates => {
+                let groups = duplicate_descriptions(&tasks, &config);
+                if groups.is_empty() {
+                    say!("No two tasks share a description.");
+                }
+                for group in groups {
+                    say!("\n{} ({})", truncate_with_ellipsis(group[0].description.trim(), 60).bold(), group.len());
+                    list_tasks(group, &config);
+                }
+
+                let pairs: Vec<(u32, u32, f64)> = similar_titles(&tasks, config.similar_title_threshold)
+                    .into_iter()
+                    .map(|(a, b, score)| (a.id, b.id, score))
+                    .collect();
+                if pairs.is_empty() {
+                    say!("No near-duplicate titles.");
+                }
+                let theme = ColorfulTheme::default();
+                for (a, b, score) in pairs {
+                    // An earlier merge in this pass may have removed one side
+                    let pair: Vec<&Task> = tasks.iter().filter(|t| t.id == a || t.id == b).collect();
+                    if pair.len() < 2 {
+                        continue;
+                    }
+                    say!("\n{} ({:.0}% similar)", "Near-duplicate titles".bold(), score * 100.0);
+                    list_tasks(pair, &config);
+                    let options = ["Keep both".to_string(), format!("Merge #{b} into #{a}"), format!("Merge #{a} into #{b}")];
+                    let (into, from) = match prompt_select(&theme, "What now?", &options, 0) {
+                        Some(1) => (a, b),
+                        Some(2) => (b, a),
+                        _ => continue,
+                    };
+                    merge_tasks(&mut tasks, into, from, &config);
+                    say!("Merged #{from} into #{into}.");
+                    log(format!("merged #{from} into #{into}"));
+                    dirty = !save_and_report(&mut tasks, &config, &mut disk);
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Report => {
+                let path = beside_data_file(&config, REPORT_FILE);
+                match std::fs::write(&path, export_report(&tasks)) {
+                    Ok(()) => say!("Report written to {}", path.display()),
+                    Err(e) => say!("Failed to write {}: {e}", path.display()),
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Todoist => {
+                let path = beside_data_file(&config, TODOIST_FILE);
+                match std::fs::write(&path, export_todoist_json(&tasks)) {
+                    Ok(()) => say!("Exported {} task(s) to {}", tasks.len(), path.display()),
+                    Err(e) => say!("Failed to write {}: {e}", path.display()),
+                }
+                wait_enter();
+            }
+
+            MenuChoice::Reload => {
+                let theme = ColorfulTheme::default();
+                if !dirty || prompt_confirm(&theme, "Discard unsaved changes and reload?", false) {
+                    match load_versioned(&config.data_file) {
+                        Ok(loaded) => {
+                            tasks = loaded;
+                            disk = DiskSnapshot::capture(&config.data_file, &tasks);
+                            dirty = false;
+                            *PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                            next_id = match tasks.iter().map(|t| t.id).max() {
+                                Some(max) => max + 1,
+                                None => args.start_id.or(config.start_id).unwrap_or(1),
+                            };
+                            say!("Reloaded {} tasks from {}", tasks.len(), config.data_file);
                         }
+                        Err(e) => say!("Could not reload {}: {e}", config.data_file),
                     }
                 }
                 wait_enter();
             }
 
+            MenuChoice::OpenDir => {
+                let dir = data_dir(&config.data_file);
+                match open_in_file_manager(&dir) {
+                    Ok(()) => say!("Opened {}", dir.display()),
+                    Err(e) => say!("Could not open a file manager ({e}). Data folder: {}", dir.display()),
+                }
+                wait_enter();
+            }
+
             MenuChoice::Exit => {
                 let theme = ColorfulTheme::default();
-                if prompt_confirm(&theme, "Quit?") {
-                    save_tasks(&tasks); // final safeguard
+                if prompt_confirm(&theme, "Quit?", true) {
+                    if !args.read_only {
+                        save_and_report(&mut tasks, &config, &mut disk); // final safeguard
+                    }
                     break;
                 }
             }
         }
+        flash = config.flash_on_action && choice.is_mutating() && disk.modified != saved_before;
     }
 
+    // Back on the normal screen, show anything the last action printed (a
+    // failed final save, say) that never got its pause
+    drop(tui_guard);
+    if let Some(overlay) = OVERLAY.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        for line in overlay.lines {
+            println!("{line}");
+        }
+    }
     println!("Goodbye!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32, title: &str) -> Task {
+        Task::new(id, title.into(), String::new(), TaskStatus::Todo)
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<u32> {
+        tasks.iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn dedupe_ids_renumbers_repeats_after_the_first() {
+        let mut tasks = vec![task(1, "a"), task(2, "b"), task(1, "c"), task(2, "d"), task(1, "e")];
+        let changes = dedupe_ids(&mut tasks);
+        assert_eq!(changes, vec![(1, 3), (2, 4), (1, 5)]);
+        assert_eq!(ids(&tasks), vec![1, 2, 3, 4, 5]);
+        // The first holder of an ID keeps it; order is untouched
+        let titles: Vec<&str> = tasks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn dedupe_ids_leaves_unique_ids_alone() {
+        let mut tasks = vec![task(3, "a"), task(1, "b"), task(7, "c")];
+        assert!(dedupe_ids(&mut tasks).is_empty());
+        assert_eq!(ids(&tasks), vec![3, 1, 7]);
+        assert!(dedupe_ids(&mut []).is_empty());
+    }
+
+    #[test]
+    fn similar_titles_ignores_case_and_punctuation() {
+        let tasks = vec![task(1, "Buy milk"), task(2, "buy  milk!"), task(3, "Walk the dog")];
+        let pairs: Vec<(u32, u32)> = similar_titles(&tasks, 0.85).iter().map(|(a, b, _)| (a.id, b.id)).collect();
+        assert_eq!(pairs, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn similar_titles_skips_titles_with_nothing_to_compare() {
+        let tasks = vec![task(1, "!!!"), task(2, "???"), task(3, "🎉"), task(4, "🎉")];
+        assert!(similar_titles(&tasks, 0.85).is_empty());
+    }
+
+    #[test]
+    fn merge_tasks_keeps_what_the_removed_task_had() {
+        let config = Config::default();
+        let mut kept = task(1, "Buy milk");
+        kept.risk = Some(RiskLevel::Low);
+        kept.points = Some(2);
+        let mut gone = Task::new(2, "buy milk!".into(), "2 litres".into(), TaskStatus::Todo);
+        gone.pinned = true;
+        gone.risk = Some(RiskLevel::High);
+        gone.url = Some("https://example.com".into());
+        gone.points = Some(5);
+        gone.waiting_on = Some("Shop opening".into());
+
+        let mut tasks = vec![kept, gone];
+        merge_tasks(&mut tasks, 1, 2, &config);
+        assert_eq!(ids(&tasks), vec![1]);
+        let t = &tasks[0];
+        assert_eq!(t.description, "2 litres");
+        assert!(t.pinned);
+        assert_eq!(t.risk, Some(RiskLevel::High));
+        assert_eq!(t.url.as_deref(), Some("https://example.com"));
+        assert_eq!(t.points, Some(2));
+        assert_eq!(t.waiting_on.as_deref(), Some("Shop opening"));
+    }
+
+    // A fresh, empty folder under the system temp dir
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn import_dir_numbers_from_next_id_and_skips_own_files() {
+        let dir = scratch_dir("import-dir");
+        let batch = serde_json::to_string(&vec![task(1, "a"), task(2, "b")]).unwrap();
+        std::fs::write(dir.join("day1.json"), &batch).unwrap();
+        std::fs::write(dir.join("tasks.json"), &batch).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not json").unwrap();
+
+        let imported = import_dir(100, dir.to_str().unwrap(), &[dir.join("tasks.json")]).unwrap();
+        assert_eq!(ids(&imported), vec![100, 101]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn import_text_numbers_from_next_id() {
+        let dir = scratch_dir("import-text");
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "Buy milk\n\n  \nx Call mum\n").unwrap();
+
+        let imported = import_text(100, path.to_str().unwrap()).unwrap();
+        assert_eq!(ids(&imported), vec![100, 101]);
+        assert_eq!(imported[0].title, "Buy milk");
+        assert_eq!(imported[0].status, TaskStatus::Todo);
+        assert_eq!(imported[1].title, "Call mum");
+        assert_eq!(imported[1].status, TaskStatus::Done);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_tasks_reads_v1_arrays_and_v2_files() {
+        let v1 = r#"[{"id": 1, "title": "a", "description": "", "status": "Todo"}]"#;
+        let v2 = r#"{"version": 2, "tasks": [{"id": 1, "title": "a", "description": "", "status": "Done", "pinned": true}]}"#;
+        let old = parse_tasks(v1).unwrap();
+        assert_eq!((ids(&old), &old[0].status, old[0].pinned), (vec![1], &TaskStatus::Todo, false));
+        let new = parse_tasks(v2).unwrap();
+        assert_eq!((ids(&new), &new[0].status, new[0].pinned), (vec![1], &TaskStatus::Done, true));
+    }
+
+    #[test]
+    fn task_values_rejects_what_it_cannot_read() {
+        let newer = serde_json::json!({"version": DATA_VERSION + 1, "tasks": []});
+        assert!(task_values(newer).unwrap_err().contains("newer"));
+        let unversioned = serde_json::json!({"tasks": []});
+        assert!(task_values(unversioned).unwrap_err().contains("version"));
+        let not_a_list = serde_json::json!({"version": 2, "tasks": {"id": 1}});
+        assert!(task_values(not_a_list).unwrap_err().contains("tasks"));
+        assert!(task_values(serde_json::json!("tasks")).is_err());
+        assert!(parse_tasks(r#"{"version": 3, "tasks": []}"#).is_err());
+    }
+
+    #[test]
+    fn save_tasks_round_trips_through_load_versioned() {
+        let dir = scratch_dir("round-trip");
+        let path = dir.join("tasks.json").to_str().unwrap().to_string();
+        let config = Config { data_file: path.clone(), ..Config::default() };
+        let mut pinned = task(2, "Buy milk");
+        pinned.pinned = true;
+        pinned.risk = Some(RiskLevel::High);
+        pinned.waiting_on = Some("Shop".into());
+        let tasks = vec![task(1, "Walk dog"), pinned];
+
+        save_tasks(&tasks, &config).unwrap();
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["version"], DATA_VERSION);
+        let loaded = load_versioned(&path).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&tasks).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn described(id: u32, description: &str) -> Task {
+        Task::new(id, format!("task {id}"), description.into(), TaskStatus::Todo)
+    }
+
+    #[test]
+    fn placeholder_descriptions_are_not_duplicates() {
+        let config = Config { empty_description_placeholder: Some("-".into()), ..Config::default() };
+        let tasks = vec![described(1, "-"), described(2, "-"), described(3, "same"), described(4, " same ")];
+        let groups: Vec<Vec<u32>> = duplicate_descriptions(&tasks, &config)
+            .iter()
+            .map(|g| g.iter().map(|t| t.id).collect())
+            .collect();
+        assert_eq!(groups, vec![vec![3, 4]]);
+    }
+
+    #[test]
+    fn merge_tasks_ignores_the_placeholder() {
+        let config = Config { empty_description_placeholder: Some("-".into()), ..Config::default() };
+        let mut tasks = vec![described(1, "keep me"), described(2, "-")];
+        merge_tasks(&mut tasks, 1, 2, &config);
+        assert_eq!(tasks[0].description, "keep me");
+
+        // A placeholder on the kept task is replaced, not appended to
+        let mut tasks = vec![described(1, "-"), described(2, "details")];
+        merge_tasks(&mut tasks, 1, 2, &config);
+        assert_eq!(ids(&tasks), vec![1]);
+        assert_eq!(tasks[0].description, "details");
+    }
+
+    #[test]
+    fn truncate_keeps_strings_that_fit() {
+        assert_eq!(truncate_with_ellipsis("abc", 3), "abc");
+        assert_eq!(truncate_with_ellipsis("abc", 10), "abc");
+        assert_eq!(truncate_with_ellipsis("", 0), "");
+    }
+
+    #[test]
+    fn truncate_counts_characters_not_bytes() {
+        assert_eq!(truncate_with_ellipsis("héllo", 5), "héllo");
+        assert_eq!(truncate_with_ellipsis("héllo", 3), "hé…");
+        assert_eq!(truncate_with_ellipsis("🎉🎉🎉", 3), "🎉🎉🎉");
+        assert_eq!(truncate_with_ellipsis("🎉🎉🎉", 2), "🎉…");
+        assert_eq!(truncate_with_ellipsis("ab🎉cd", 3), "ab…");
+        assert_eq!(truncate_with_ellipsis("ab🎉cd", 4), "ab🎉…");
+    }
+
+    #[test]
+    fn truncate_to_zero_or_one_character() {
+        assert_eq!(truncate_with_ellipsis("abc", 0), "");
+        assert_eq!(truncate_with_ellipsis("abc", 1), "…");
+        assert_eq!(truncate_with_ellipsis("a", 1), "a");
+        assert_eq!(truncate_with_ellipsis("🎉", 1), "🎉");
+    }
+
+    #[test]
+    fn spin_off_keeps_pin_risk_and_link_only() {
+        let mut parent = task(1, "parent");
+        parent.pinned = true;
+        parent.risk = Some(RiskLevel::High);
+        parent.url = Some("https://example.com".into());
+        parent.points = Some(5);
+        parent.waiting_on = Some("Sam".into());
+
+        let child = parent.spin_off(2, "child".into(), "desc".into(), TaskStatus::InProgress);
+        assert_eq!((child.id, child.title.as_str(), child.description.as_str()), (2, "child", "desc"));
+        assert_eq!(child.status, TaskStatus::InProgress);
+        assert!(child.pinned);
+        assert_eq!(child.risk, Some(RiskLevel::High));
+        assert_eq!(child.url.as_deref(), Some("https://example.com"));
+        assert_eq!((child.points, child.waiting_on), (None, None));
+    }
+
+    #[test]
+    fn risk_banner_names_open_high_risk_tasks() {
+        let mut tasks = vec![task(1, "Deploy"), task(2, "Migrate"), task(3, "Tidy"), task(4, "Old")];
+        tasks[0].risk = Some(RiskLevel::High);
+        tasks[1].risk = Some(RiskLevel::High);
+        tasks[2].risk = Some(RiskLevel::Medium);
+        tasks[3].risk = Some(RiskLevel::High);
+        tasks[3].status = TaskStatus::Done;
+        assert_eq!(risk_banner(&tasks).as_deref(), Some("⚠ 2 open High-risk task(s): #1 Deploy, #2 Migrate"));
+
+        tasks[0].risk = None;
+        tasks[1].status = TaskStatus::Done;
+        assert_eq!(risk_banner(&tasks), None);
+    }
+
+    #[test]
+    fn allow_new_tasks_caps_at_max_tasks_when_blocking() {
+        let mut config = Config::default();
+        assert_eq!(allow_new_tasks(100, 7, &config), 7);
+
+        config.max_tasks = Some(5);
+        config.block_at_max_tasks = true;
+        assert_eq!(allow_new_tasks(2, 3, &config), 3);
+        assert_eq!(allow_new_tasks(2, 4, &config), 3);
+        assert_eq!(allow_new_tasks(5, 1, &config), 0);
+        // Already over a lowered limit
+        assert_eq!(allow_new_tasks(8, 2, &config), 0);
+        assert!(!allow_new_task(5, &config));
+        assert!(allow_new_task(4, &config));
+    }
+
+    fn titles(tasks: &[Task]) -> Vec<(u32, &str)> {
+        tasks.iter().map(|t| (t.id, t.title.as_str())).collect()
+    }
+
+    #[test]
+    fn merge_external_takes_whichever_side_changed() {
+        let base = DiskSnapshot::capture("", &[task(1, "a"), task(2, "b"), task(3, "c"), task(4, "d")]).tasks;
+        // Here: #2 edited, #3 deleted, #5 added
+        let mut mine = vec![task(1, "a"), task(2, "b here"), task(4, "d"), task(5, "new here")];
+        // There: #1 edited, #4 deleted, #5 added
+        let disk = vec![task(1, "a there"), task(2, "b"), task(3, "c"), task(5, "new there")];
+
+        let summary = merge_external(&mut mine, disk, &base, |_, _| panic!("no conflicts expected"));
+        assert_eq!(titles(&mine), vec![(1, "a there"), (2, "b here"), (5, "new here"), (6, "new there")]);
+        assert_eq!(summary, MergeSummary { added: 1, updated: 1, removed: 1 });
+    }
+
+    #[test]
+    fn merge_external_asks_when_both_sides_changed() {
+        let base = DiskSnapshot::capture("", &[task(1, "a"), task(2, "b"), task(3, "c")]).tasks;
+        // #1 edited on both sides, #2 edited here and deleted there,
+        // #3 deleted here and edited there
+        let mut mine = vec![task(1, "a here"), task(2, "b here")];
+        let disk = vec![task(1, "a there"), task(3, "c there")];
+
+        let mut asked = Vec::new();
+        let summary = merge_external(&mut mine, disk, &base, |mine, theirs| {
+            asked.push((mine.map(|t| t.id), theirs.map(|t| t.id)));
+            // Side with the file every time
+            true
+        });
+        assert_eq!(asked, vec![(Some(1), Some(1)), (Some(2), None), (None, Some(3))]);
+        assert_eq!(titles(&mine), vec![(1, "a there"), (3, "c there")]);
+        assert_eq!(summary, MergeSummary { added: 1, updated: 1, removed: 1 });
+    }
+
+    #[test]
+    fn merge_external_skips_identical_edits() {
+        let base = DiskSnapshot::capture("", &[task(1, "a")]).tasks;
+        let mut mine = vec![task(1, "same")];
+        let summary = merge_external(&mut mine, vec![task(1, "same")], &base, |_, _| panic!("nothing to ask"));
+        assert_eq!(titles(&mine), vec![(1, "same")]);
+        assert_eq!(summary, MergeSummary::default());
+    }
+
+    // Records what a TerminalGuard asks of the terminal
+    #[derive(Default)]
+    struct FakeScreen {
+        events: Vec<&'static str>,
+        fail_enter: bool,
+    }
+
+    impl Screen for FakeScreen {
+        fn enter(&mut self) -> io::Result<()> {
+            self.events.push("enter");
+            if self.fail_enter { Err(io::Error::other("no tty")) } else { Ok(()) }
+        }
+
+        fn leave(&mut self) {
+            self.events.push("leave");
+        }
+    }
+
+    // Shaped like `run_menu_tui`: draws until a draw fails on `fail_at`
+    fn draw_loop(screen: &mut FakeScreen, fail_at: Option<usize>) -> Result<(), AppError> {
+        let guard = TerminalGuard::enter(screen)?;
+        for frame in 0..5 {
+            if Some(frame) == fail_at {
+                Err(io::Error::other("draw failed"))?;
+            }
+            guard.screen.events.push("draw");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn terminal_guard_restores_after_an_error_mid_loop() {
+        let mut screen = FakeScreen::default();
+        assert!(draw_loop(&mut screen, Some(2)).is_err());
+        assert_eq!(screen.events, vec!["enter", "draw", "draw", "leave"]);
+    }
+
+    #[test]
+    fn terminal_guard_restores_once_on_normal_return() {
+        let mut screen = FakeScreen::default();
+        assert!(draw_loop(&mut screen, None).is_ok());
+        assert_eq!(screen.events.first(), Some(&"enter"));
+        assert_eq!(screen.events.iter().filter(|e| **e == "leave").count(), 1);
+        assert_eq!(screen.events.last(), Some(&"leave"));
+    }
+
+    #[test]
+    fn terminal_guard_undoes_a_failed_enter() {
+        let mut screen = FakeScreen { fail_enter: true, ..Default::default() };
+        assert!(draw_loop(&mut screen, None).is_err());
+        assert_eq!(screen.events, vec!["enter", "leave"]);
+    }
+
+    // Renders the menu into an off-screen buffer of the given size
+    fn render_menu(width: u16, height: u16) -> ratatui::buffer::Buffer {
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw_menu(f, f.area(), MENU_ITEMS, false, false, None)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    #[test]
+    fn draw_menu_survives_tiny_terminals() {
+        for (w, h) in [(0, 0), (1, 1), (0, 5), (5, 0), (1, 40), (80, 1), (2, 2), (3, 3)] {
+            render_menu(w, h);
+        }
+    }
+
+    fn screen_rows(buffer: &ratatui::buffer::Buffer) -> Vec<String> {
+        let width = buffer.area.width as usize;
+        let cells: Vec<&str> = buffer.content().iter().map(|c| c.symbol()).collect();
+        cells.chunks(width.max(1)).map(|row| row.concat()).collect()
+    }
+
+    #[test]
+    fn draw_menu_keeps_every_title_on_short_terminals() {
+        // 80x24 is too short for subtitles and dividers; 80x10 needs columns
+        for (w, h) in [(80, 24), (80, 10), (100, 6)] {
+            let rows = screen_rows(&render_menu(w, h));
+            for it in MENU_ITEMS {
+                assert!(rows.iter().any(|r| r.contains(it.title)), "{:?} missing at {w}x{h}", it.title);
+            }
+            let footer = rows.last().unwrap();
+            assert!(footer.contains("q to quit"), "footer cut off at {w}x{h}: {footer:?}");
+        }
+    }
+
+    #[test]
+    fn draw_menu_shows_subtitles_when_there_is_room() {
+        let rows = screen_rows(&render_menu(80, 80));
+        for it in MENU_ITEMS {
+            assert!(rows.iter().any(|r| r.contains(it.sub)), "{:?} missing", it.sub);
+        }
+    }
+
+    fn render_overlay(width: u16, height: u16, lines: &[&str], ask: &Ask) -> (ratatui::buffer::Buffer, (u16, u16)) {
+        let backdrop = render_menu(width, height);
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+        let mut max_scroll = (0, 0);
+        terminal.draw(|f| max_scroll = draw_overlay(f, &backdrop, &lines, ask)).unwrap();
+        (terminal.backend().buffer().clone(), max_scroll)
+    }
+
+    #[test]
+    fn draw_overlay_shows_the_transcript_above_the_question() {
+        let ask = Ask::Confirm { prompt: "Add another?", default: true };
+        let (buffer, _) = render_overlay(80, 24, &["Task #3 updated."], &ask);
+        let rows = screen_rows(&buffer);
+        let at = |text: &str| rows.iter().position(|r| r.contains(text));
+        assert!(at("Task #3 updated.") < at("Add another? [Y/n]"));
+        // The menu stays visible around the box
+        assert!(rows.last().unwrap().contains("q to quit"));
+    }
+
+    #[test]
+    fn draw_overlay_follows_the_end_of_a_long_transcript() {
+        let lines: Vec<String> = (1..=50).map(|i| format!("line {i}")).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let items = [Line::from("Keep mine"), Line::from("Take the file's")];
+        let ask = Ask::Select { prompt: "Which one?", items: &items, selected: 1 };
+        let (buffer, max_scroll) = render_overlay(80, 24, &lines, &ask);
+        let rows = screen_rows(&buffer);
+        assert!(rows.iter().any(|r| r.contains("line 50")));
+        assert!(!rows.iter().any(|r| r.contains("line 1 ")));
+        assert!(rows.iter().any(|r| r.contains("› Take the file's")));
+        assert!(max_scroll.0 > 0);
+
+        // A pause starts from the top instead
+        let (buffer, _) = render_overlay(80, 24, &lines, &Ask::Pause { scroll: (0, 0) });
+        let rows = screen_rows(&buffer);
+        assert!(rows.iter().any(|r| r.contains("line 1 ")));
+        assert!(rows.iter().any(|r| r.contains("to scroll")));
+    }
+
+    #[test]
+    fn draw_overlay_survives_tiny_terminals() {
+        let ask = Ask::Text { prompt: "Title", value: "Buy milk", cursor: 3, error: Some("Title cannot be empty") };
+        for (w, h) in [(0, 0), (1, 1), (2, 2), (3, 3), (80, 1), (1, 40), (19, 9)] {
+            render_overlay(w, h, &["x"], &ask);
+        }
+    }
+
+    #[test]
+    fn ansi_line_turns_sgr_codes_into_styles() {
+        let mut style = Style::default();
+        let line = ansi_line("\x1b[1;31mred\x1b[0m plain \x1b[38;5;208morange", &mut style);
+        let spans: Vec<(&str, Style)> = line.spans.iter().map(|s| (s.content.as_ref(), s.style)).collect();
+        assert_eq!(spans, vec![
+            ("red", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            (" plain ", Style::default()),
+            ("orange", Style::default().fg(Color::Indexed(208))),
+        ]);
+        // An open color runs on into the next line
+        let next = ansi_line("still orange\x1b[K", &mut style);
+        assert_eq!(next.spans.len(), 1);
+        assert_eq!(next.spans[0].style.fg, Some(Color::Indexed(208)));
+        assert_eq!(next.spans[0].content, "still orange");
+    }
+
+    #[test]
+    fn tsv_field_escapes_separators_and_backslashes() {
+        assert_eq!(tsv_field("plain title"), "plain title");
+        assert_eq!(tsv_field("a\tb"), "a\\tb");
+        assert_eq!(tsv_field("a\nb"), "a\\nb");
+        assert_eq!(tsv_field("a\r\nb"), "a\\r\\nb");
+        assert_eq!(tsv_field("C:\\temp"), "C:\\\\temp");
+        // An escaped backslash can't be mistaken for the start of an escape
+        assert_eq!(tsv_field("\\t"), "\\\\t");
+        assert!(!tsv_field("x\ty\nz\r").contains(['\t', '\n', '\r']));
+    }
+
+    fn args(list: &[&str]) -> Result<Args, String> {
+        parse_args(list.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parse_args_wants_tsv_with_status() {
+        assert_eq!(args(&["--status", "todo"]).unwrap_err(), "--status only works together with --tsv");
+        let parsed = args(&["--status", "done", "--tsv"]).unwrap();
+        assert!(parsed.tsv);
+        assert_eq!(parsed.status, Some(TaskStatus::Done));
+    }
+}